@@ -48,6 +48,12 @@ import (
 	"errors"
 	"fmt"
 	"io"
+	"os"
+	"os/user"
+	"strconv"
+	"strings"
+	"sync/atomic"
+	"unsafe"
 
 	"github.com/klauspost/compress/zstd"
 	"github.com/nwaples/rardecode"
@@ -63,14 +69,423 @@ const successCode C.uchar = C.SUCCESS_CODE
 
 const unexpectedNilEncounteredErrorMessage string = "Unexpected nil encountered"
 
+// RAR5 header types
+const (
+	rarFiveHeaderTypeFile       uint64 = 2
+	rarFiveHeaderTypeEncryption uint64 = 4
+	rarFiveHeaderTypeEnd        uint64 = 5
+)
+
+// RAR5 file header flags, header flags, and extra record types
+const (
+	rarFiveFileFlagModificationTime uint64 = 0x0002
+	rarFiveFileFlagCrc              uint64 = 0x0004
+	rarFiveHeaderFlagExtraArea      uint64 = 0x0001
+	rarFiveHeaderFlagData           uint64 = 0x0002
+	rarFiveExtraRecordRedirection   uint64 = 5
+	rarFiveExtraRecordOwner         uint64 = 6
+	rarFiveHostOsWindows            uint64 = 0
+)
+
+// RAR5 file redirection types
+const (
+	rarRedirectionUnixSymlink    uint64 = 1
+	rarRedirectionWindowsSymlink uint64 = 2
+	rarRedirectionJunction       uint64 = 3
+	rarRedirectionHardLink       uint64 = 4
+	rarRedirectionFileCopy       uint64 = 5
+)
+
+// RAR5 owner record flags
+const (
+	rarOwnerFlagUserName       uint64 = 0x01
+	rarOwnerFlagGroupName      uint64 = 0x02
+	rarOwnerFlagNumericUserId  uint64 = 0x04
+	rarOwnerFlagNumericGroupId uint64 = 0x08
+)
+
+// Set on converted entries that were links or file copies, so that the Rust code can list them
+const paxRedirectionKey string = "TARX.rar.redirection"
+const paxRedirectionTargetKey string = "TARX.rar.redirection_target"
+
+var rarFiveSignature = []uint8("Rar!\x1a\x07\x01\x00")
+
+var errCancelled = errors.New("cancelled")
+
+var errTruncatedHeader = errors.New("truncated RAR5 header")
+
+// Set by the Rust code (from a signal handler) when the process is interrupted
+func IsCancelled(cancelled *C.int) bool {
+	return atomic.LoadInt32((*int32)(unsafe.Pointer(cancelled))) != 0
+}
+
+// Fails once more than max bytes have been written. A negative max means no limit.
+type LimitedWriter struct {
+	max     int64
+	wr      io.Writer
+	written int64
+}
+
+func (li *LimitedWriter) Write(ui []uint8) (int, error) {
+	if li.max >= 0 && li.written+int64(len(ui)) > li.max {
+		return 0, fmt.Errorf("decompressed data is more than %d bytes", li.max)
+	}
+
+	in, er := li.wr.Write(ui)
+
+	li.written += int64(in)
+
+	return in, er
+}
+
+// Fails with errCancelled once the process is interrupted, so that decompression stops between chunks
+type CancellableReader struct {
+	cancelled *C.int
+	re        io.Reader
+}
+
+func (ca CancellableReader) Read(ui []uint8) (int, error) {
+	if IsCancelled(ca.cancelled) {
+		return 0, errCancelled
+	}
+
+	return ca.re.Read(ui)
+}
+
+// A file redirection record of a RAR5 file header (a link, or a copy of an earlier file)
+type RarRedirection struct {
+	kind   uint64
+	target string
+}
+
+func (ra RarRedirection) Description() string {
+	switch ra.kind {
+	case rarRedirectionUnixSymlink:
+		return "symlink"
+	case rarRedirectionWindowsSymlink:
+		return "Windows symlink"
+	case rarRedirectionJunction:
+		return "junction"
+	case rarRedirectionHardLink:
+		return "hard link"
+	case rarRedirectionFileCopy:
+		return "file copy"
+	default:
+		return fmt.Sprintf("redirection of type %d", ra.kind)
+	}
+}
+
+// A Unix owner record of a RAR5 file header, which is only stored if the archive was created with "-ow". Any of the
+// fields may be missing.
+type RarOwner struct {
+	groupId   *uint64
+	groupName string
+	userId    *uint64
+	userName  string
+}
+
+// A RAR5 file header, with its file redirection record and owner record (if any)
+type RarFileHeader struct {
+	name        string
+	owner       *RarOwner
+	redirection *RarRedirection
+}
+
+// Reads the fields of a RAR5 header. Once a read fails, later reads return zero values, and er is set.
+type RarFiveReader struct {
+	er error
+	po int
+	ui []uint8
+}
+
+// Reads a variable-length integer (7 bits per byte, least significant first)
+func (ra *RarFiveReader) Vint() uint64 {
+	var value uint64
+
+	for index := 0; index < 10; index++ {
+		if ra.er != nil || ra.po >= len(ra.ui) {
+			ra.er = errTruncatedHeader
+
+			return 0
+		}
+
+		by := ra.ui[ra.po]
+
+		ra.po++
+
+		value |= uint64(by&0x7F) << (7 * index)
+
+		if by&0x80 == 0 {
+			return value
+		}
+	}
+
+	ra.er = errors.New("invalid variable-length integer in RAR5 header")
+
+	return 0
+}
+
+func (ra *RarFiveReader) Bytes(length uint64) []uint8 {
+	if ra.er != nil || length > uint64(len(ra.ui)-ra.po) {
+		ra.er = errTruncatedHeader
+
+		return nil
+	}
+
+	ui := ra.ui[ra.po : ra.po+int(length)]
+
+	ra.po += int(length)
+
+	return ui
+}
+
+// rardecode does not expose the file redirection records that RAR5 archives use for links and file copies, so the
+// headers are scanned separately. Returns nil for RAR 1.5-4.x archives (which store symlink targets as the contents
+// of the entry, and do not store hard links), and for archives whose headers are encrypted.
+func ScanRarFiveFileHeaders(dataUintEightArray []uint8) ([]RarFileHeader, error) {
+	// Self-extracting archives start with an executable
+	in := bytes.Index(dataUintEightArray, rarFiveSignature)
+
+	if in < 0 {
+		return nil, nil
+	}
+
+	offset := in + len(rarFiveSignature)
+
+	var fileHeaders []RarFileHeader
+
+	for offset < len(dataUintEightArray) {
+		// Skip the CRC-32 of the header
+		ra := RarFiveReader{po: offset + 4, ui: dataUintEightArray}
+
+		headerSize := ra.Vint()
+
+		if ra.er != nil {
+			return nil, ra.er
+		}
+
+		if headerSize > uint64(len(dataUintEightArray)-ra.po) {
+			return nil, errTruncatedHeader
+		}
+
+		headerEnd := ra.po + int(headerSize)
+
+		// Fields cannot be read past the end of the header
+		ra.ui = dataUintEightArray[:headerEnd]
+
+		headerType := ra.Vint()
+		headerFlags := ra.Vint()
+
+		var extraAreaSize, dataSize uint64
+
+		if headerFlags&rarFiveHeaderFlagExtraArea != 0 {
+			extraAreaSize = ra.Vint()
+		}
+
+		if headerFlags&rarFiveHeaderFlagData != 0 {
+			dataSize = ra.Vint()
+		}
+
+		if ra.er != nil {
+			return nil, ra.er
+		}
+
+		switch headerType {
+		case rarFiveHeaderTypeEncryption:
+			return nil, nil
+		case rarFiveHeaderTypeEnd:
+			return fileHeaders, nil
+		case rarFiveHeaderTypeFile:
+			if extraAreaSize > uint64(headerEnd-ra.po) {
+				return nil, errTruncatedHeader
+			}
+
+			fileHeader, er := ParseRarFiveFileHeader(&ra, headerEnd-int(extraAreaSize))
+
+			if er != nil {
+				return nil, er
+			}
+
+			fileHeaders = append(fileHeaders, fileHeader)
+		}
+
+		if dataSize > uint64(len(dataUintEightArray)-headerEnd) {
+			return nil, errTruncatedHeader
+		}
+
+		offset = headerEnd + int(dataSize)
+	}
+
+	return fileHeaders, nil
+}
+
+// ra is positioned after the common header fields. The extra area starts at extraAreaStart, and ends at the end of
+// ra.ui.
+func ParseRarFiveFileHeader(ra *RarFiveReader, extraAreaStart int) (RarFileHeader, error) {
+	fileFlags := ra.Vint()
+
+	// Unpacked size and attributes
+	ra.Vint()
+	ra.Vint()
+
+	if fileFlags&rarFiveFileFlagModificationTime != 0 {
+		ra.Bytes(4)
+	}
+
+	if fileFlags&rarFiveFileFlagCrc != 0 {
+		ra.Bytes(4)
+	}
+
+	// Compression information
+	ra.Vint()
+
+	hostOs := ra.Vint()
+
+	name := string(ra.Bytes(ra.Vint()))
+
+	var owner *RarOwner
+	var redirection *RarRedirection
+
+	ra.po = extraAreaStart
+
+	for ra.er == nil && ra.po < len(ra.ui) {
+		recordSize := ra.Vint()
+
+		recordStart := ra.po
+
+		if recordSize > uint64(len(ra.ui)-recordStart) {
+			return RarFileHeader{}, errTruncatedHeader
+		}
+
+		switch ra.Vint() {
+		case rarFiveExtraRecordOwner:
+			owner = ParseRarFiveOwner(ra)
+		case rarFiveExtraRecordRedirection:
+			kind := ra.Vint()
+
+			// Flags (whether the target is a directory)
+			ra.Vint()
+
+			target := string(ra.Bytes(ra.Vint()))
+
+			// Windows links use backslashes, and junctions are stored as NT paths (e.g. "\??\C:\dir")
+			if hostOs == rarFiveHostOsWindows || kind == rarRedirectionWindowsSymlink || kind == rarRedirectionJunction {
+				target = strings.ReplaceAll(strings.TrimPrefix(target, "\\??\\"), "\\", "/")
+			}
+
+			redirection = &RarRedirection{kind: kind, target: target}
+		}
+
+		ra.po = recordStart + int(recordSize)
+	}
+
+	if ra.er != nil {
+		return RarFileHeader{}, ra.er
+	}
+
+	return RarFileHeader{name: name, owner: owner, redirection: redirection}, nil
+}
+
+// ra is positioned after the type of the owner record
+func ParseRarFiveOwner(ra *RarFiveReader) *RarOwner {
+	var owner RarOwner
+
+	flags := ra.Vint()
+
+	if flags&rarOwnerFlagUserName != 0 {
+		owner.userName = string(ra.Bytes(ra.Vint()))
+	}
+
+	if flags&rarOwnerFlagGroupName != 0 {
+		owner.groupName = string(ra.Bytes(ra.Vint()))
+	}
+
+	if flags&rarOwnerFlagNumericUserId != 0 {
+		id := ra.Vint()
+
+		owner.userId = &id
+	}
+
+	if flags&rarOwnerFlagNumericGroupId != 0 {
+		id := ra.Vint()
+
+		owner.groupId = &id
+	}
+
+	return &owner
+}
+
+// Like tar, a name that exists on this system is preferred over the numeric ID. Returns false if neither is usable.
+func ResolveRarOwnerId(name string, id *uint64, lookup func(string) (string, error)) (int, bool) {
+	if name != "" {
+		st, er := lookup(name)
+
+		if er == nil {
+			in, erro := strconv.Atoi(st)
+
+			if erro == nil {
+				return in, true
+			}
+		}
+	}
+
+	if id != nil && *id <= uint64(^uint32(0)) {
+		return int(*id), true
+	}
+
+	return 0, false
+}
+
+// Sets the owner and group of a converted entry. Uname and Gname are only set if the corresponding ID is known (to the
+// name, or to the numeric ID if the name is not stored), since the Rust code ignores IDs without a name.
+func SetTarHeaderOwner(he *tar.Header, owner *RarOwner) {
+	uid, ok := ResolveRarOwnerId(owner.userName, owner.userId, func(st string) (string, error) {
+		us, er := user.Lookup(st)
+
+		if er != nil {
+			return "", er
+		}
+
+		return us.Uid, nil
+	})
+
+	if ok {
+		he.Uid = uid
+		he.Uname = owner.userName
+
+		if he.Uname == "" {
+			he.Uname = strconv.Itoa(uid)
+		}
+	}
+
+	gid, ok := ResolveRarOwnerId(owner.groupName, owner.groupId, func(st string) (string, error) {
+		gr, er := user.LookupGroup(st)
+
+		if er != nil {
+			return "", er
+		}
+
+		return gr.Gid, nil
+	})
+
+	if ok {
+		he.Gid = gid
+		he.Gname = owner.groupName
+
+		if he.Gname == "" {
+			he.Gname = strconv.Itoa(gid)
+		}
+	}
+}
+
 func main() {}
 
 //export ConvertRarToTar
-func ConvertRarToTar(dataPointerAndLength PointerAndLength, passwordPointerAndLength PointerAndLength) ConvertRarToTarReturnType {
+func ConvertRarToTar(dataPointerAndLength PointerAndLength, passwordPointerAndLength PointerAndLength, cancelled *C.int, maxEntrySize C.longlong, maxSize C.longlong) ConvertRarToTarReturnType {
 	dataUintEightArray := PointerAndLengthToUintEightArray(dataPointerAndLength)
 	passwordUintEightArray := PointerAndLengthToUintEightArray(passwordPointerAndLength)
 
-	ui, er := ConvertRarToTarInner(dataUintEightArray, passwordUintEightArray)
+	ui, er := ConvertRarToTarInner(dataUintEightArray, passwordUintEightArray, cancelled, int64(maxEntrySize), int64(maxSize))
 
 	if er != nil {
 		erString := fmt.Sprint(er)
@@ -98,7 +513,7 @@ func ConvertRarToTar(dataPointerAndLength PointerAndLength, passwordPointerAndLe
 }
 
 // Referenced https://medium.com/@s.vvardenfell/creating-in-memory-tar-archive-in-go-golang-83b7ca309602
-func ConvertRarToTarInner(dataUintEightArray []uint8, passwordUintEightArray []uint8) ([]uint8, error) {
+func ConvertRarToTarInner(dataUintEightArray []uint8, passwordUintEightArray []uint8, cancelled *C.int, maxEntrySize int64, maxSize int64) ([]uint8, error) {
 	// TODO
 	// How much memory should be preallocated?
 	in := len(dataUintEightArray) * 4
@@ -114,10 +529,34 @@ func ConvertRarToTarInner(dataUintEightArray []uint8, passwordUintEightArray []u
 			return nil, er
 		}
 
+		fileHeaders, erro := ScanRarFiveFileHeaders(dataUintEightArray)
+
+		if erro != nil {
+			// rardecode may still be able to read the archive, but links and file copies are then extracted as empty
+			// files
+			fmt.Fprintf(os.Stderr, "WARNING: Could not scan RAR5 headers for links and file copies: %s\n", erro)
+		}
+
+		// Paths of files that are copied by later entries, and their contents once they have been read
+		copyTargets := map[string]bool{}
+		copiedFiles := map[string][]uint8{}
+
+		for _, ra := range fileHeaders {
+			if ra.redirection != nil && ra.redirection.kind == rarRedirectionFileCopy {
+				copyTargets[ra.redirection.target] = true
+			}
+		}
+
 		{
-			wr := tar.NewWriter(bu)
+			wr := tar.NewWriter(&LimitedWriter{max: maxSize, wr: bu})
+
+			fileIndex := 0
 
 			for {
+				if IsCancelled(cancelled) {
+					return nil, errCancelled
+				}
+
 				fi, err := re.Next()
 
 				if err != nil {
@@ -130,32 +569,57 @@ func ConvertRarToTarInner(dataUintEightArray []uint8, passwordUintEightArray []u
 
 				unPackedSize := fi.UnPackedSize
 
+				name := fi.Name
+
+				// Checked before the buffer is allocated
+				if maxEntrySize >= 0 && unPackedSize > maxEntrySize {
+					return nil, fmt.Errorf("\"%s\" is %d bytes, more than the maximum of %d bytes", name, unPackedSize, maxEntrySize)
+				}
+
 				// TODO
 				// Is this the right amount of memory to preallocate?
 				buf := bytes.NewBuffer(make([]uint8, 0, unPackedSize))
 
-				intS, erro := io.Copy(buf, re)
+				intS, erro := io.Copy(&LimitedWriter{max: maxEntrySize, wr: buf}, CancellableReader{cancelled: cancelled, re: re})
 
 				if erro != nil {
-					return nil, erro
+					return nil, fmt.Errorf("\"%s\": %w", name, erro)
 				}
 
-				name := fi.Name
-
 				if intS != unPackedSize {
-					fmt.Printf("WARNING: Mismatch between number of actually read bytes (%d) and size reported in header (%d) when processing file \"%s\"", intS, unPackedSize, name)
+					fmt.Fprintf(os.Stderr, "WARNING: Mismatch between number of actually read bytes (%d) and size reported in header (%d) when processing file \"%s\"\n", intS, unPackedSize, name)
 				}
 
+				// rardecode returns the file headers in order
+				var owner *RarOwner
+				var redirection *RarRedirection
+
+				if fileIndex < len(fileHeaders) && fileHeaders[fileIndex].name == name {
+					owner = fileHeaders[fileIndex].owner
+					redirection = fileHeaders[fileIndex].redirection
+				}
+
+				fileIndex++
+
 				fil := fi.Mode()
 
 				var typeflag uint8
 
 				if fil.IsDir() {
 					typeflag = tar.TypeDir
-				} else if fil.IsRegular() {
+				} else if fil.IsRegular() || redirection != nil {
 					typeflag = tar.TypeReg
+				} else if fil&os.ModeSymlink != 0 {
+					// RAR 1.5-4.x store the target of a symlink as its contents
+					if buf.Len() == 0 {
+						fmt.Fprintf(os.Stderr, "WARNING: Skipping symlink \"%s\", since its target could not be read\n", name)
+
+						continue
+					}
+
+					redirection = &RarRedirection{kind: rarRedirectionUnixSymlink, target: buf.String()}
 				} else {
-					return nil, errors.New("unexpected file type encountered")
+					return nil, fmt.Errorf("\"%s\" has an unexpected file type (%s)", name, fil.Type())
 				}
 
 				he := tar.Header{
@@ -166,16 +630,62 @@ func ConvertRarToTarInner(dataUintEightArray []uint8, passwordUintEightArray []u
 					Typeflag: typeflag,
 				}
 
+				if owner != nil {
+					SetTarHeaderOwner(&he, owner)
+				}
+
+				if redirection != nil {
+					// Targets of hard links and file copies are paths of entries in the archive, which the Rust code
+					// resolves within the new directory
+					switch redirection.kind {
+					case rarRedirectionUnixSymlink, rarRedirectionWindowsSymlink, rarRedirectionJunction:
+						he.Typeflag = tar.TypeSymlink
+						he.Linkname = redirection.target
+					case rarRedirectionHardLink:
+						he.Typeflag = tar.TypeLink
+						he.Linkname = redirection.target
+					case rarRedirectionFileCopy:
+						copiedFile, ok := copiedFiles[redirection.target]
+
+						if ok {
+							buf = bytes.NewBuffer(copiedFile)
+						} else {
+							// The target was not extracted (yet), so a hard link is the closest thing. The Rust code
+							// skips it if the target does not exist.
+							he.Typeflag = tar.TypeLink
+							he.Linkname = redirection.target
+						}
+					default:
+						return nil, fmt.Errorf("\"%s\" is a %s, which is not supported", name, redirection.Description())
+					}
+
+					if he.Typeflag != tar.TypeReg {
+						buf.Reset()
+					}
+
+					// The type bits of the mode cannot be stored in PAX headers
+					he.Mode = int64(fil.Perm())
+					he.Size = int64(buf.Len())
+					he.PAXRecords = map[string]string{
+						paxRedirectionKey:       redirection.Description(),
+						paxRedirectionTargetKey: redirection.target,
+					}
+				}
+
+				if copyTargets[name] && he.Typeflag == tar.TypeReg {
+					copiedFiles[name] = bytes.Clone(buf.Bytes())
+				}
+
 				errorR := wr.WriteHeader(&he)
 
 				if errorR != nil {
-					return nil, errorR
+					return nil, fmt.Errorf("\"%s\": %w", name, errorR)
 				}
 
 				_, errorRr := io.Copy(wr, buf)
 
 				if errorRr != nil {
-					return nil, errorRr
+					return nil, fmt.Errorf("\"%s\": %w", name, errorRr)
 				}
 			}
 
@@ -189,10 +699,10 @@ func ConvertRarToTarInner(dataUintEightArray []uint8, passwordUintEightArray []u
 }
 
 //export DecompressBzipTwo
-func DecompressBzipTwo(dataPointerAndLength PointerAndLength) DecompressBzipTwoReturnType {
+func DecompressBzipTwo(dataPointerAndLength PointerAndLength, cancelled *C.int, maxSize C.longlong) DecompressBzipTwoReturnType {
 	dataUintEightArray := PointerAndLengthToUintEightArray(dataPointerAndLength)
 
-	ui, er := DecompressBzipTwoInner(dataUintEightArray)
+	ui, er := DecompressBzipTwoInner(dataUintEightArray, cancelled, int64(maxSize))
 
 	if er != nil {
 		erString := fmt.Sprint(er)
@@ -219,7 +729,7 @@ func DecompressBzipTwo(dataPointerAndLength PointerAndLength) DecompressBzipTwoR
 	}
 }
 
-func DecompressBzipTwoInner(dataUintEightArray []uint8) ([]uint8, error) {
+func DecompressBzipTwoInner(dataUintEightArray []uint8, cancelled *C.int, maxSize int64) ([]uint8, error) {
 	// TODO
 	// How much memory should be preallocated?
 	in := len(dataUintEightArray) * 4
@@ -229,7 +739,7 @@ func DecompressBzipTwoInner(dataUintEightArray []uint8) ([]uint8, error) {
 	{
 		re := bzip2.NewReader(bytes.NewBuffer(dataUintEightArray))
 
-		_, er := io.Copy(bu, re)
+		_, er := io.Copy(&LimitedWriter{max: maxSize, wr: bu}, CancellableReader{cancelled: cancelled, re: re})
 
 		if er != nil {
 			return nil, er
@@ -242,10 +752,10 @@ func DecompressBzipTwoInner(dataUintEightArray []uint8) ([]uint8, error) {
 }
 
 //export DecompressZstd
-func DecompressZstd(dataPointerAndLength PointerAndLength) DecompressZstdReturnType {
+func DecompressZstd(dataPointerAndLength PointerAndLength, cancelled *C.int, maxSize C.longlong) DecompressZstdReturnType {
 	dataUintEightArray := PointerAndLengthToUintEightArray(dataPointerAndLength)
 
-	ui, er := DecompressZstdInner(dataUintEightArray)
+	ui, er := DecompressZstdInner(dataUintEightArray, cancelled, int64(maxSize))
 
 	if er != nil {
 		erString := fmt.Sprint(er)
@@ -272,7 +782,7 @@ func DecompressZstd(dataPointerAndLength PointerAndLength) DecompressZstdReturnT
 	}
 }
 
-func DecompressZstdInner(dataUintEightArray []uint8) ([]uint8, error) {
+func DecompressZstdInner(dataUintEightArray []uint8, cancelled *C.int, maxSize int64) ([]uint8, error) {
 	// TODO
 	// How much memory should be preallocated?
 	in := len(dataUintEightArray) * 4
@@ -286,7 +796,7 @@ func DecompressZstdInner(dataUintEightArray []uint8) ([]uint8, error) {
 			return nil, er
 		}
 
-		_, err := io.Copy(bu, de)
+		_, err := io.Copy(&LimitedWriter{max: maxSize, wr: bu}, CancellableReader{cancelled: cancelled, re: de})
 
 		if err != nil {
 			de.Close()
@@ -0,0 +1,215 @@
+use crate::extract::{
+    self,
+    sparse::{self, PaxSparseFile, WriteHole},
+    Member,
+};
+use anyhow::Context;
+use sevenz_rust::SevenZReader;
+use std::{
+    io::{self, BufWriter, ErrorKind, Read, Seek, StdoutLock, Write},
+    path::Path,
+};
+use tar::{Archive, Entry};
+use zip::ZipArchive;
+
+/// Calls `write` with a buffered writer for standard output. The reader of standard output closing it early (e.g.
+/// `tarx cat archive.tar file.txt | head`) is not treated as an error.
+pub fn write_to_stdout(
+    write: impl FnOnce(&mut BufWriter<StdoutLock>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut stdout_buf_writer = BufWriter::new(io::stdout().lock());
+
+    let result = write(&mut stdout_buf_writer).and_then(|()| Ok(stdout_buf_writer.flush()?));
+
+    match result {
+        // The error can have context added, or be the source of another error (e.g. from a decoder)
+        Err(er)
+            if er.chain().any(|err| {
+                err.downcast_ref::<io::Error>()
+                    .is_some_and(|erro| erro.kind() == ErrorKind::BrokenPipe)
+            }) =>
+        {
+            Ok(())
+        }
+        re => re,
+    }
+}
+
+/// Copies the contents of `member` from a tar archive to `writer`. If `member` is a hard link, `get_archive` is called
+/// again to find its target.
+pub fn write_tar_member<R: Read>(
+    get_archive: impl Fn() -> anyhow::Result<Archive<R>>,
+    member: &Member,
+    writer: &mut impl WriteHole,
+) -> anyhow::Result<()> {
+    let mut archive = get_archive()?;
+
+    for re in archive.entries()? {
+        let mut entry = re?;
+
+        if extract::is_tar_metadata_entry(entry.header().entry_type()) {
+            continue;
+        }
+
+        let pax_sparse_file = PaxSparseFile::from_entry(&mut entry)?;
+
+        if !member
+            .is_entry_path(sparse::get_tar_entry_path(&entry, pax_sparse_file.as_ref())?.as_path())
+        {
+            continue;
+        }
+
+        let entry_type = entry.header().entry_type();
+
+        anyhow::ensure!(!entry_type.is_dir(), "\"{}\" is a directory", member.path());
+
+        if entry_type.is_symlink() {
+            let link_name = entry.link_name()?.unwrap_or_default();
+
+            anyhow::bail!(
+                "\"{}\" is a symbolic link to \"{}\"",
+                member.path(),
+                link_name.display()
+            );
+        }
+
+        if entry_type.is_hard_link() {
+            let link_name = entry
+                .link_name()?
+                .context("Could not get target of hard link")?
+                .into_owned();
+
+            return write_tar_hard_link_target(get_archive()?, link_name.as_path(), writer);
+        }
+
+        return copy_tar_entry(&mut entry, pax_sparse_file.as_ref(), writer);
+    }
+
+    anyhow::bail!("\"{}\" was not found in the archive", member.path());
+}
+
+/// The data of a hard link is stored in the entry of its target
+fn write_tar_hard_link_target<R: Read>(
+    mut archive: Archive<R>,
+    link_name: &Path,
+    writer: &mut impl WriteHole,
+) -> anyhow::Result<()> {
+    for re in archive.entries()? {
+        let mut entry = re?;
+
+        if extract::is_tar_metadata_entry(entry.header().entry_type()) {
+            continue;
+        }
+
+        let pax_sparse_file = PaxSparseFile::from_entry(&mut entry)?;
+
+        if !extract::is_same_entry_path(
+            sparse::get_tar_entry_path(&entry, pax_sparse_file.as_ref())?.as_path(),
+            link_name,
+        ) {
+            continue;
+        }
+
+        anyhow::ensure!(
+            entry.header().entry_type().is_file(),
+            "Target of hard link \"{}\" is not a regular file",
+            link_name.display()
+        );
+
+        return copy_tar_entry(&mut entry, pax_sparse_file.as_ref(), writer);
+    }
+
+    anyhow::bail!(
+        "Target of hard link \"{}\" was not found in the archive",
+        link_name.display()
+    );
+}
+
+/// Sparse files are expanded, since the data of their entries is stored without the holes
+fn copy_tar_entry<R: Read>(
+    entry: &mut Entry<R>,
+    pax_sparse_file: Option<&PaxSparseFile>,
+    writer: &mut impl WriteHole,
+) -> anyhow::Result<()> {
+    match pax_sparse_file {
+        Some(sp) => sparse::copy_pax_sparse_file(entry, writer, sp)?,
+        None => {
+            io::copy(entry, writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the contents of `member` from a zip archive to `writer`
+pub fn write_zip_member<R: Read + Seek>(
+    zip_archive: &mut ZipArchive<R>,
+    password: Option<&[u8]>,
+    member: &Member,
+    writer: &mut impl Write,
+) -> anyhow::Result<()> {
+    for us in 0_usize..zip_archive.len() {
+        // Reading the raw entry does not require a password
+        let (is_member, is_directory) = {
+            let raw_zip_file = zip_archive.by_index_raw(us)?;
+
+            (
+                raw_zip_file
+                    .enclosed_name()
+                    .is_some_and(|pa| member.is_entry_path(pa.as_path())),
+                raw_zip_file.is_dir(),
+            )
+        };
+
+        if !is_member {
+            continue;
+        }
+
+        anyhow::ensure!(!is_directory, "\"{}\" is a directory", member.path());
+
+        let mut zip_file = match password {
+            Some(sl) => zip_archive.by_index_decrypt(us, sl)?,
+            None => zip_archive.by_index(us)?,
+        };
+
+        io::copy(&mut zip_file, writer)?;
+
+        return Ok(());
+    }
+
+    anyhow::bail!("\"{}\" was not found in the archive", member.path());
+}
+
+/// Copies the contents of `member` from a 7z archive to `writer`
+pub fn write_seven_z_member<R: Read + Seek>(
+    seven_z_reader: &mut SevenZReader<R>,
+    member: &Member,
+    writer: &mut impl Write,
+) -> anyhow::Result<()> {
+    // `for_each_entries` requires the closure to return a `sevenz_rust::Error`, so the actual result is stored here
+    let mut result_option = None::<anyhow::Result<()>>;
+
+    seven_z_reader.for_each_entries(|se, re| {
+        if !member.is_entry_path(Path::new(se.name())) {
+            // The entries of a block are decompressed in order, so the data of other entries still has to be read
+            io::copy(re, &mut io::sink())?;
+
+            return Ok(true);
+        }
+
+        let result = if se.is_directory() {
+            Err(anyhow::anyhow!("\"{}\" is a directory", member.path()))
+        } else {
+            io::copy(re, writer)
+                .map(|_| ())
+                .map_err(anyhow::Error::from)
+        };
+
+        result_option = Some(result);
+
+        // Stop processing entries
+        Ok(false)
+    })?;
+
+    result_option.with_context(|| format!("\"{}\" was not found in the archive", member.path()))?
+}
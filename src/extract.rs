@@ -0,0 +1,1210 @@
+pub mod dry_run;
+pub mod free_space;
+pub mod journal;
+pub mod limits;
+pub mod overwrite;
+pub mod ownership;
+pub mod safe_path;
+pub mod sparse;
+pub mod special_file;
+pub mod symlink;
+pub mod sync;
+pub mod timestamps;
+pub mod transform;
+pub mod xattr;
+
+use crate::new_directory::NewDirectory;
+use anyhow::Context;
+use dry_run::DryRunSummary;
+use glob::{MatchOptions, Pattern};
+use journal::{Journal, JournalState};
+use limits::{LimitTracker, Limits};
+use overwrite::{ExistingAction, OverwritePolicy};
+use ownership::{OwnershipOptions, OwnershipTracker};
+use safe_path::{sanitize_entry_path, SafePathResolver};
+use sparse::SparseWriter;
+use special_file::{SpecialFile, SPECIAL_MODE_BITS};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::OsStr,
+    fs::{self, OpenOptions},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use sync::SyncTracker;
+use tar::EntryType;
+use timestamps::EntryTimes;
+use transform::Transform;
+use xattr::{Xattr, XattrOptions, XattrTracker};
+
+/// Files created by the file managers of macOS and Windows. Matched case-insensitively.
+const OS_METADATA_FILE_NAMES: [&str; 3_usize] = [".DS_Store", "Thumbs.db", "desktop.ini"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    // "*" does not match "/", but "**" does
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Options that affect which entries are extracted, and where they are extracted to
+pub struct ExtractOptions {
+    /// Report what would be extracted, without writing anything
+    pub dry_run: bool,
+    pub exclude_patterns: Vec<Pattern>,
+    /// Extract even if the destination file system does not appear to have enough free space
+    pub force: bool,
+    pub include_patterns: Vec<Pattern>,
+    /// Extract all files directly into the new directory, without the directories containing them. Directory entries
+    /// are skipped.
+    pub junk_paths: bool,
+    /// Keep the setuid, setgid, and sticky bits of entries
+    pub keep_special_bits: bool,
+    pub limits: Limits,
+    pub members: Vec<Member>,
+    pub overwrite_policy: OverwritePolicy,
+    pub ownership: OwnershipOptions,
+    /// Continue an extraction that did not finish, skipping the entries its journal records as completed
+    pub resume: bool,
+    /// Skip metadata created by macOS and Windows (see `is_os_metadata`)
+    pub skip_os_metadata: bool,
+    pub smart_unpack: bool,
+    /// Skip runs of zeros instead of writing them, so that they become holes (see `SparseWriter`)
+    pub sparse: bool,
+    /// Create device nodes and FIFOs, instead of skipping them
+    pub special_files: bool,
+    pub strip_components: usize,
+    /// Flush every extracted file and directory to disk before the new directory is moved into place (see
+    /// `SyncTracker`)
+    pub sync: bool,
+    /// Applied in order to the path of each entry, after leading components have been stripped
+    pub transforms: Vec<Transform>,
+    pub xattr_options: XattrOptions,
+}
+
+/// What kind of file an entry is extracted as
+pub enum EntryKind {
+    Directory,
+    File,
+    /// Contains the path (in the archive) of the target
+    HardLink(PathBuf),
+    /// A device node or FIFO
+    Special(SpecialFile),
+    /// Contains the target
+    Symlink(PathBuf),
+    /// An entry type that is not recognized, which is extracted as a regular file (like other tar implementations do)
+    Other,
+}
+
+/// Information about an entry that is needed before it is extracted
+pub struct EntryInfo {
+    /// Last access time, if the archive stores it
+    pub access_time: Option<SystemTime>,
+    /// CRC-32 of the contents, if the archive stores it
+    pub crc32: Option<u32>,
+    /// Creation time, if the archive stores it (only set on Windows)
+    pub creation_time: Option<SystemTime>,
+    /// Group ID, if the archive stores one
+    pub gid: Option<u32>,
+    pub kind: EntryKind,
+    /// Unix mode, if the archive stores one
+    pub mode: Option<u32>,
+    pub modification_time: Option<SystemTime>,
+    /// Uncompressed size
+    pub size: u64,
+    /// User ID, if the archive stores one
+    pub uid: Option<u32>,
+    /// Extended attributes, including ACLs and SELinux labels (only stored by tar archives)
+    pub xattrs: Vec<Xattr>,
+}
+
+/// An entry (or directory of entries) that was explicitly requested on the command line
+pub struct Member {
+    found: Cell<bool>,
+    path: String,
+    sanitized_path: PathBuf,
+}
+
+impl Member {
+    pub fn new(path: String) -> anyhow::Result<Member> {
+        let sanitized_path = sanitize_entry_path(Path::new(path.as_str()))
+            .filter(|pa| !pa.as_os_str().is_empty())
+            .with_context(|| format!("\"{path}\" is not a valid member path"))?;
+
+        Ok(Member {
+            found: Cell::new(false),
+            path,
+            sanitized_path,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.as_str()
+    }
+
+    /// Whether `entry_path` is the path of this member itself (and not of an entry inside of it)
+    pub fn is_entry_path(&self, entry_path: &Path) -> bool {
+        is_same_entry_path(self.sanitized_path.as_path(), entry_path)
+    }
+}
+
+impl ExtractOptions {
+    /// Fails if any of the requested members did not match an entry of the archive
+    pub fn ensure_members_found(&self) -> anyhow::Result<()> {
+        let not_found = self
+            .members
+            .iter()
+            .filter(|me| !me.found.get())
+            .map(|me| me.path.as_str())
+            .collect::<Vec<_>>();
+
+        anyhow::ensure!(
+            not_found.is_empty(),
+            "The following members were not found in the archive:\n{}",
+            not_found.join("\n")
+        );
+
+        Ok(())
+    }
+
+    /// Returns the top-level directory of the archive (after leading components have been stripped), if the archive
+    /// has exactly one top-level entry and that entry is a directory. `entry_paths` yields the path of each entry, and
+    /// whether that entry is a directory.
+    pub fn find_root_directory(
+        &self,
+        entry_paths: impl IntoIterator<Item = (PathBuf, bool)>,
+    ) -> Option<PathBuf> {
+        let mut root_directory_option = None::<PathBuf>;
+
+        for (pa, is_directory) in entry_paths {
+            let Some(sanitized_entry_path) = sanitize_entry_path(pa.as_path()) else {
+                continue;
+            };
+
+            if !self.is_entry_selected(sanitized_entry_path.as_path()) {
+                continue;
+            }
+
+            let Some(stripped_entry_path) =
+                self.rename_entry_path(sanitized_entry_path.as_path(), is_directory)
+            else {
+                continue;
+            };
+
+            let mut components = stripped_entry_path.components();
+
+            let Some(first_component) = components.next() else {
+                continue;
+            };
+
+            // A file at the top level of the archive
+            if components.next().is_none() && !is_directory {
+                return None;
+            }
+
+            let first_component_path = Path::new(first_component.as_os_str());
+
+            match root_directory_option {
+                Some(ref pat) => {
+                    if pat.as_path() != first_component_path {
+                        return None;
+                    }
+                }
+                None => {
+                    root_directory_option = Some(first_component_path.to_path_buf());
+                }
+            }
+        }
+
+        root_directory_option
+    }
+
+    /// Whether the entry is one of the requested members or inside one of them (if any members were requested), is
+    /// matched by the include patterns (if there are any), and is not matched by any exclude pattern. A pattern
+    /// matches an entry if it matches the path of the entry, or the path of a directory containing the entry. If
+    /// `skip_os_metadata` is set, metadata created by macOS and Windows is never selected.
+    pub fn is_entry_selected(&self, entry_path: &Path) -> bool {
+        let sanitized_entry_path =
+            sanitize_entry_path(entry_path).unwrap_or_else(|| entry_path.to_path_buf());
+
+        if self.skip_os_metadata && is_os_metadata(sanitized_entry_path.as_path()) {
+            return false;
+        }
+
+        if !self.members.is_empty() {
+            let mut is_member = false;
+
+            for me in &self.members {
+                if sanitized_entry_path.starts_with(me.sanitized_path.as_path()) {
+                    me.found.set(true);
+
+                    is_member = true;
+                }
+            }
+
+            if !is_member {
+                return false;
+            }
+        }
+
+        let is_matched_by_any = |patterns: &[Pattern]| {
+            sanitized_entry_path.ancestors().any(|pa| {
+                !pa.as_os_str().is_empty()
+                    && patterns
+                        .iter()
+                        .any(|pat| pat.matches_path_with(pa, MATCH_OPTIONS))
+            })
+        };
+
+        (self.include_patterns.is_empty() || is_matched_by_any(self.include_patterns.as_slice()))
+            && !is_matched_by_any(self.exclude_patterns.as_slice())
+    }
+
+    /// Returns the path that the entry is extracted to, relative to the new directory (see `rename_entry_path`).
+    /// Returns `None` if the path of the entry, or the path produced by the transforms, contains a ".." component.
+    pub fn get_transformed_entry_path(
+        &self,
+        entry_path: &Path,
+        is_directory: bool,
+    ) -> Option<PathBuf> {
+        let sanitized_entry_path = sanitize_entry_path(entry_path)?;
+
+        self.rename_entry_path(sanitized_entry_path.as_path(), is_directory)
+    }
+
+    /// Removes the first `strip_components` components from `sanitized_entry_path`, and then applies the transforms.
+    /// The result is sanitized again, so `None` is returned if a transform produces a path containing a ".."
+    /// component. Transforms are not applied to paths that are not valid UTF-8. If `junk_paths` is set, only the file
+    /// name is kept.
+    fn rename_entry_path(
+        &self,
+        sanitized_entry_path: &Path,
+        is_directory: bool,
+    ) -> Option<PathBuf> {
+        let stripped_entry_path = sanitized_entry_path
+            .components()
+            .skip(self.strip_components)
+            .collect::<PathBuf>();
+
+        let transformed_entry_path = match stripped_entry_path.to_str() {
+            Some(st) if !self.transforms.is_empty() => {
+                // Like in archives, directory paths end with "/", so that e.g. "s,^pkg-1.2/,pkg/," also renames the
+                // directory itself
+                let mut transformed_entry_path = if is_directory {
+                    format!("{st}/")
+                } else {
+                    st.to_owned()
+                };
+
+                for tr in &self.transforms {
+                    transformed_entry_path = tr.apply(transformed_entry_path.as_str()).into_owned();
+                }
+
+                sanitize_entry_path(Path::new(transformed_entry_path.as_str()))?
+            }
+            _ => stripped_entry_path,
+        };
+
+        if self.junk_paths {
+            return Some(
+                transformed_entry_path
+                    .file_name()
+                    .map(PathBuf::from)
+                    .unwrap_or_default(),
+            );
+        }
+
+        Some(transformed_entry_path)
+    }
+}
+
+/// Decides where each entry of an archive is written to
+pub struct Extractor<'a> {
+    /// Set once every entry has been extracted, since creating an entry in a directory changes its modification time
+    directory_times: RefCell<Vec<(PathBuf, EntryTimes)>>,
+    dry_run_summary: DryRunSummary,
+    /// Set when the user answers "All" or "None" when asked what to do with an existing file
+    existing_action_for_all: Cell<Option<ExistingAction>>,
+    extract_options: &'a ExtractOptions,
+    /// Not created during a dry run
+    journal: Option<Journal>,
+    limit_tracker: LimitTracker<'a>,
+    new_directory: NewDirectory,
+    ownership_tracker: OwnershipTracker,
+    /// Entries that were already extracted by the extraction being resumed
+    resumed_entries: Cell<u64>,
+    root_directory: Option<PathBuf>,
+    safe_path_resolver: SafePathResolver,
+    skipped_os_metadata: Cell<u64>,
+    skipped_special_files: Cell<u64>,
+    stripped_special_bits: Cell<u64>,
+    /// Symlinks that were written as regular files containing the target, since symlinks are disabled
+    symlinks_written_as_files: Cell<u64>,
+    /// Only set with "--sync", and not during a dry run
+    sync_tracker: Option<SyncTracker>,
+    xattr_tracker: XattrTracker,
+}
+
+impl Extractor<'_> {
+    /// `root_directory` is the top-level directory of the archive whose contents should be extracted directly
+    /// into `new_directory` (see `ExtractOptions::find_root_directory`)
+    pub fn new(
+        extract_options: &ExtractOptions,
+        new_directory: NewDirectory,
+        root_directory: Option<PathBuf>,
+    ) -> anyhow::Result<Extractor<'_>> {
+        let safe_path_resolver =
+            SafePathResolver::new(new_directory.path(), extract_options.dry_run)?;
+
+        if let Some(ref pa) = root_directory {
+            tracing::info!(
+                root_directory = %pa.display(),
+                "Archive has a single top-level directory, extracting its contents directly"
+            );
+        }
+
+        let journal = if extract_options.dry_run {
+            None
+        } else {
+            new_directory.record_created(
+                new_directory
+                    .path()
+                    .join(journal::JOURNAL_FILE_NAME)
+                    .as_path(),
+            );
+
+            Some(Journal::open(new_directory.path(), extract_options.resume)?)
+        };
+
+        let sync_tracker = (extract_options.sync && !extract_options.dry_run)
+            .then(|| SyncTracker::new(new_directory.path()));
+
+        Ok(Extractor {
+            directory_times: RefCell::new(Vec::new()),
+            dry_run_summary: DryRunSummary::default(),
+            existing_action_for_all: Cell::new(None),
+            extract_options,
+            journal,
+            limit_tracker: LimitTracker::new(&extract_options.limits),
+            new_directory,
+            ownership_tracker: OwnershipTracker::default(),
+            resumed_entries: Cell::new(0_u64),
+            root_directory,
+            safe_path_resolver,
+            skipped_os_metadata: Cell::new(0_u64),
+            skipped_special_files: Cell::new(0_u64),
+            stripped_special_bits: Cell::new(0_u64),
+            symlinks_written_as_files: Cell::new(0_u64),
+            sync_tracker,
+            xattr_tracker: XattrTracker::default(),
+        })
+    }
+
+    /// Fails if the file system containing the new directory does not have enough free space for `total_size` bytes
+    /// (the uncompressed size of the selected entries). Entries already extracted by the extraction being resumed are
+    /// not counted. Skipped with "--force" and during a dry run.
+    pub fn ensure_free_space(&self, total_size: u64) -> anyhow::Result<()> {
+        if self.extract_options.force {
+            return Ok(());
+        }
+
+        let Some(ref jo) = self.journal else {
+            return Ok(());
+        };
+
+        free_space::ensure_free_space(
+            self.new_directory.path(),
+            total_size.saturating_sub(jo.get_earlier_completed_size()),
+        )
+    }
+
+    /// Called for metadata entries of tar archives (see `is_tar_metadata_entry`), which are not extracted, but count
+    /// towards "--max-entries" like every other entry
+    pub fn check_metadata_entry(&self, entry_path: &Path) -> anyhow::Result<()> {
+        self.limit_tracker.check_entry(entry_path)
+    }
+
+    /// Returns `None` if the entry should not be extracted
+    pub fn get_destination_path(
+        &self,
+        entry_path: &Path,
+        is_directory: bool,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        self.limit_tracker.check_entry(entry_path)?;
+
+        let Some(sanitized_entry_path) = sanitize_entry_path(entry_path) else {
+            if self.extract_options.dry_run {
+                self.dry_run_summary.report_skipped(
+                    entry_path,
+                    "path would be extracted outside of the new directory",
+                )?;
+            } else {
+                tracing::warn!(
+                    entry_path = %entry_path.display(),
+                    "Skipping entry with a path that would be extracted outside of the new directory"
+                );
+            }
+
+            return Ok(None);
+        };
+
+        if self.extract_options.skip_os_metadata && is_os_metadata(sanitized_entry_path.as_path()) {
+            self.skipped_os_metadata
+                .set(self.skipped_os_metadata.get().saturating_add(1_u64));
+
+            if self.extract_options.dry_run {
+                self.dry_run_summary
+                    .report_skipped(entry_path, "macOS or Windows metadata")?;
+            }
+
+            return Ok(None);
+        }
+
+        if !self
+            .extract_options
+            .is_entry_selected(sanitized_entry_path.as_path())
+        {
+            if self.extract_options.dry_run {
+                self.dry_run_summary
+                    .report_skipped(entry_path, "not selected")?;
+            }
+
+            return Ok(None);
+        }
+
+        if self.extract_options.junk_paths && is_directory {
+            if self.extract_options.dry_run {
+                self.dry_run_summary.report_skipped(
+                    entry_path,
+                    "directories are not extracted with --junk-paths",
+                )?;
+            }
+
+            return Ok(None);
+        }
+
+        let Some(stripped_entry_path) = self
+            .extract_options
+            .rename_entry_path(sanitized_entry_path.as_path(), is_directory)
+        else {
+            if self.extract_options.dry_run {
+                self.dry_run_summary.report_skipped(
+                    entry_path,
+                    "transformed path would be extracted outside of the new directory",
+                )?;
+            } else {
+                tracing::warn!(
+                    entry_path = %entry_path.display(),
+                    "Skipping entry with a transformed path that would be extracted outside of the new directory"
+                );
+            }
+
+            return Ok(None);
+        };
+
+        let Some(destination_path) = self.join_new_directory(stripped_entry_path.as_path()) else {
+            return Ok(None);
+        };
+
+        // The journal would be overwritten by the entry, and the entry removed with the journal
+        if destination_path == self.new_directory.path().join(journal::JOURNAL_FILE_NAME) {
+            if self.extract_options.dry_run {
+                self.dry_run_summary
+                    .report_skipped(entry_path, "path is reserved for the journal of tarx")?;
+            } else {
+                tracing::warn!(
+                    entry_path = %entry_path.display(),
+                    "Skipping entry with a path that is reserved for the journal of tarx"
+                );
+            }
+
+            return Ok(None);
+        }
+
+        Ok(Some(destination_path))
+    }
+
+    /// Returns where the target of a hard link entry was extracted to. Unlike `get_destination_path`, this does not
+    /// take the include and exclude patterns or the requested members into account. Returns `None` if the target
+    /// was not extracted. Fails if the target resolves to a location outside of the new directory.
+    pub fn get_hard_link_target_path(&self, link_name: &Path) -> anyhow::Result<Option<PathBuf>> {
+        let Some(hard_link_target_path) = self
+            .extract_options
+            .get_transformed_entry_path(link_name, false)
+            .and_then(|pa| self.join_new_directory(pa.as_path()))
+        else {
+            return Ok(None);
+        };
+
+        // `symlink_metadata` so that a hard link to a symlink works
+        if fs::symlink_metadata(hard_link_target_path.as_path()).is_err() {
+            return Ok(None);
+        }
+
+        // The target itself is not followed if it is a symlink, but its parent directories are
+        if let Some(pa) = hard_link_target_path.parent() {
+            self.safe_path_resolver.ensure_inside(pa)?;
+        }
+
+        Ok(Some(hard_link_target_path))
+    }
+
+    fn join_new_directory(&self, stripped_entry_path: &Path) -> Option<PathBuf> {
+        let relative_path = match self.root_directory {
+            Some(ref pa) => stripped_entry_path.strip_prefix(pa).ok()?,
+            None => stripped_entry_path,
+        };
+
+        // Returns `None` for the top-level directory itself, an entry with no more than `strip_components`
+        // components, or an entry without a name
+        self.safe_path_resolver.resolve(relative_path)
+    }
+
+    /// Applies the overwrite policy if `destination_path` already exists. Returns the path the entry should be written
+    /// to, or `None` if the entry should be skipped. Existing directories are reused for directory entries. During a
+    /// dry run, what would be done is reported, and `None` is always returned.
+    pub fn prepare_destination(
+        &self,
+        destination_path: PathBuf,
+        entry_info: &EntryInfo,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        if self.is_resumed(destination_path.as_path(), entry_info)? {
+            return Ok(None);
+        }
+
+        if let EntryKind::Special(ref sp) = entry_info.kind {
+            if let Some(reason) = self.get_special_file_skip_reason(sp) {
+                self.skipped_special_files
+                    .set(self.skipped_special_files.get().saturating_add(1_u64));
+
+                if self.extract_options.dry_run {
+                    self.dry_run_summary
+                        .report_skipped(destination_path.as_path(), reason)?;
+                } else {
+                    tracing::warn!(
+                        entry_path = %self.get_relative_path(destination_path.as_path()).display(),
+                        kind = sp.kind.description(),
+                        reason,
+                        "Skipping special file"
+                    );
+                }
+
+                return Ok(None);
+            }
+        }
+
+        // Entries of other types are extracted as regular files. The size of the remaining kinds of entries is not
+        // necessarily the size of what is written.
+        if matches!(entry_info.kind, EntryKind::File | EntryKind::Other) {
+            self.limit_tracker.add_entry_size(
+                self.get_relative_path(destination_path.as_path()),
+                entry_info.size,
+            )?;
+        }
+
+        let Ok(metadata) = fs::symlink_metadata(destination_path.as_path()) else {
+            return self.plan("create", destination_path, entry_info, None);
+        };
+
+        if matches!(entry_info.kind, EntryKind::Directory) && metadata.is_dir() {
+            let existing_mode = {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+
+                    Some(metadata.permissions().mode())
+                }
+
+                #[cfg(not(unix))]
+                {
+                    None
+                }
+            };
+
+            return self.plan("update", destination_path, entry_info, existing_mode);
+        }
+
+        let existing_action = match (
+            self.extract_options.dry_run,
+            self.extract_options.overwrite_policy,
+        ) {
+            (true, OverwritePolicy::Ask) => {
+                self.dry_run_summary.report_conflict(
+                    destination_path.as_path(),
+                    "already exists, would ask what to do",
+                )?;
+
+                return Ok(None);
+            }
+            (true, OverwritePolicy::Error) => {
+                self.dry_run_summary
+                    .report_conflict(destination_path.as_path(), "already exists")?;
+
+                return Ok(None);
+            }
+            (_, overwrite_policy) => overwrite_policy.get_existing_action(
+                destination_path.as_path(),
+                &metadata,
+                entry_info.modification_time,
+                || {
+                    if let Some(ex) = self.existing_action_for_all.get() {
+                        return Ok(ex);
+                    }
+
+                    let (existing_action, for_all) =
+                        overwrite::ask_existing_action(destination_path.as_path())?;
+
+                    if for_all {
+                        self.existing_action_for_all.set(Some(existing_action));
+                    }
+
+                    Ok(existing_action)
+                },
+            )?,
+        };
+
+        match existing_action {
+            ExistingAction::Overwrite => {
+                if self.extract_options.dry_run {
+                    if metadata.is_dir() {
+                        self.dry_run_summary.report_conflict(
+                            destination_path.as_path(),
+                            "is an existing directory, and cannot be overwritten",
+                        )?;
+
+                        return Ok(None);
+                    }
+
+                    return self.plan("overwrite", destination_path, entry_info, None);
+                }
+
+                overwrite::remove_existing(
+                    destination_path.as_path(),
+                    &metadata,
+                    &self.new_directory,
+                )?;
+
+                self.report_stripped_special_bits(destination_path.as_path(), entry_info);
+
+                self.record_started(destination_path.as_path())?;
+
+                Ok(Some(destination_path))
+            }
+            ExistingAction::Rename => {
+                let unique_path = overwrite::find_unique_path(destination_path.as_path())?;
+
+                tracing::info!(
+                    destination_path = %destination_path.display(),
+                    unique_path = %unique_path.display(),
+                    "Destination already exists, extracting under a new name"
+                );
+
+                self.plan("create", unique_path, entry_info, None)
+            }
+            ExistingAction::Skip => {
+                if self.extract_options.dry_run {
+                    self.dry_run_summary
+                        .report_skipped(destination_path.as_path(), "destination already exists")?;
+                } else {
+                    tracing::info!(
+                        destination_path = %destination_path.display(),
+                        "Destination already exists, skipping entry"
+                    );
+                }
+
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns `destination_path`, unless this is a dry run, in which case `action` is reported instead
+    fn plan(
+        &self,
+        action: &str,
+        destination_path: PathBuf,
+        entry_info: &EntryInfo,
+        existing_mode: Option<u32>,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        self.report_stripped_special_bits(destination_path.as_path(), entry_info);
+
+        if !self.extract_options.dry_run {
+            self.record_started(destination_path.as_path())?;
+
+            return Ok(Some(destination_path));
+        }
+
+        self.dry_run_summary.report_planned(
+            action,
+            destination_path.as_path(),
+            entry_info,
+            self.get_mode(entry_info),
+            existing_mode,
+        )?;
+
+        Ok(None)
+    }
+
+    /// `destination_path` relative to the new directory (which may be a staging directory)
+    fn get_relative_path<'b>(&self, destination_path: &'b Path) -> &'b Path {
+        destination_path
+            .strip_prefix(self.new_directory.path())
+            .unwrap_or(destination_path)
+    }
+
+    /// Returns why a device node or FIFO is not extracted, or `None` if it is
+    fn get_special_file_skip_reason(&self, special_file: &SpecialFile) -> Option<&'static str> {
+        if !self.extract_options.special_files {
+            return Some("device nodes and FIFOs are only extracted with --special-files");
+        }
+
+        if special_file.kind.is_device() && special_file.device.is_none() {
+            return Some("the archive does not store the device number");
+        }
+
+        None
+    }
+
+    /// The mode that the entry is extracted with. The setuid, setgid, and sticky bits are removed, unless
+    /// "--keep-special-bits" was passed.
+    pub fn get_mode(&self, entry_info: &EntryInfo) -> Option<u32> {
+        let mode = entry_info.mode?;
+
+        Some(if self.extract_options.keep_special_bits {
+            mode & 0o7777_u32
+        } else {
+            mode & 0o777_u32
+        })
+    }
+
+    fn report_stripped_special_bits(&self, destination_path: &Path, entry_info: &EntryInfo) {
+        if self.extract_options.keep_special_bits {
+            return;
+        }
+
+        let Some(ut) = entry_info.mode else {
+            return;
+        };
+
+        // Symlinks do not have their own permissions
+        if ut & SPECIAL_MODE_BITS == 0_u32 || matches!(entry_info.kind, EntryKind::Symlink(_)) {
+            return;
+        }
+
+        self.stripped_special_bits
+            .set(self.stripped_special_bits.get().saturating_add(1_u64));
+
+        tracing::warn!(
+            entry_path = %self.get_relative_path(destination_path).display(),
+            mode = format!("{:o}", ut & 0o7777_u32),
+            "Removing setuid, setgid, and sticky bits (use \"--keep-special-bits\" to keep them)"
+        );
+    }
+
+    /// Whether the entry was already extracted by the extraction being resumed. If it was only partially extracted, it
+    /// is removed, unless the archive stores a CRC-32 that it turns out to match.
+    fn is_resumed(&self, destination_path: &Path, entry_info: &EntryInfo) -> anyhow::Result<bool> {
+        let Some(ref jo) = self.journal else {
+            return Ok(false);
+        };
+
+        let Some(earlier_state) = jo.get_earlier_state(destination_path) else {
+            return Ok(false);
+        };
+
+        let Ok(metadata) = fs::symlink_metadata(destination_path) else {
+            return Ok(false);
+        };
+
+        // Existing directories are reused, and their permissions are set again
+        if metadata.is_dir() {
+            return Ok(false);
+        }
+
+        let is_complete = match *earlier_state {
+            JournalState::Completed { crc32, size } => {
+                !metadata.is_file()
+                    || (metadata.len() == size
+                        && size == entry_info.size
+                        && entry_info.crc32.is_none_or(|ut| ut == crc32))
+            }
+            JournalState::Started => match entry_info.crc32 {
+                Some(ut) if metadata.is_file() && metadata.len() == entry_info.size => {
+                    journal::compute_file_crc32(destination_path)? == ut
+                }
+                _ => false,
+            },
+        };
+
+        if !is_complete {
+            tracing::info!(
+                destination_path = %destination_path.display(),
+                "Entry was not completely extracted, extracting it again"
+            );
+
+            overwrite::remove_existing(destination_path, &metadata, &self.new_directory)?;
+
+            return Ok(false);
+        }
+
+        if matches!(*earlier_state, JournalState::Started) {
+            jo.record_completed(
+                destination_path,
+                metadata.len(),
+                entry_info.crc32.unwrap_or_default(),
+            )?;
+        }
+
+        self.resumed_entries
+            .set(self.resumed_entries.get().saturating_add(1_u64));
+
+        Ok(true)
+    }
+
+    /// Called before an entry is written to `destination_path`
+    fn record_started(&self, destination_path: &Path) -> anyhow::Result<()> {
+        self.new_directory.record_created(destination_path);
+
+        if let Some(ref jo) = self.journal {
+            jo.record_started(destination_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Called after an entry has been written to `destination_path`. Sets its owner and group (see
+    /// `OwnershipOptions`), extended attributes (see `XattrOptions`), and times, and records its size and CRC-32 in the
+    /// journal, so that it is not extracted again if the extraction is resumed.
+    pub fn complete_entry(
+        &self,
+        destination_path: &Path,
+        entry_info: &EntryInfo,
+    ) -> anyhow::Result<()> {
+        // Entries of other types are not necessarily written (e.g. `tar::Entry::unpack` skips some of them), and their
+        // metadata is only set if they were
+        let Ok(metadata) = fs::symlink_metadata(destination_path) else {
+            if matches!(entry_info.kind, EntryKind::Other) {
+                tracing::debug!(
+                    entry_path = %self.get_relative_path(destination_path).display(),
+                    "Entry of an unrecognized type was not extracted"
+                );
+
+                return Ok(());
+            }
+
+            anyhow::bail!(
+                "Extracted entry \"{}\" does not exist",
+                self.get_relative_path(destination_path).display()
+            );
+        };
+
+        // A hard link shares the owner of its target
+        if !matches!(entry_info.kind, EntryKind::HardLink(_))
+            && self.ownership_tracker.apply(
+                &self.extract_options.ownership,
+                destination_path,
+                self.get_relative_path(destination_path),
+                entry_info.uid,
+                entry_info.gid,
+            )
+        {
+            self.restore_special_bits(destination_path, entry_info)?;
+        }
+
+        // Set last, since changing the owner removes some extended attributes (e.g. "security.capability"), and
+        // changing the permissions changes ACLs
+        if !matches!(entry_info.kind, EntryKind::HardLink(_)) {
+            self.xattr_tracker.apply(
+                &self.extract_options.xattr_options,
+                destination_path,
+                self.get_relative_path(destination_path),
+                entry_info.xattrs.as_slice(),
+            );
+        }
+
+        let entry_times = EntryTimes {
+            access: entry_info.access_time,
+            creation: entry_info.creation_time,
+            modification: entry_info.modification_time,
+        };
+
+        match entry_info.kind {
+            EntryKind::Directory => {
+                self.directory_times
+                    .borrow_mut()
+                    .push((destination_path.to_path_buf(), entry_times));
+            }
+            // A hard link shares the times of its target
+            EntryKind::HardLink(_) => {}
+            EntryKind::File | EntryKind::Other | EntryKind::Special(_) | EntryKind::Symlink(_) => {
+                entry_times.apply(destination_path)?;
+            }
+        }
+
+        if let Some(ref sy) = self.sync_tracker {
+            // A hard link shares the contents of its target, which was already synced
+            sy.sync_entry(
+                destination_path,
+                metadata.is_file() && !matches!(entry_info.kind, EntryKind::HardLink(_)),
+                metadata.is_dir(),
+            )?;
+        }
+
+        let Some(ref jo) = self.journal else {
+            return Ok(());
+        };
+
+        // The CRC-32 is only compared with the one stored in the archive (see `is_resumed`), so it is not computed when
+        // the archive does not store one, which would mean reading every extracted file again
+        let (size, crc32) = if metadata.is_file() {
+            (metadata.len(), entry_info.crc32.unwrap_or_default())
+        } else {
+            (0_u64, 0_u32)
+        };
+
+        jo.record_completed(destination_path, size, crc32)
+    }
+
+    /// Creates a symlink for formats whose symlinks are not created by their library (e.g. zip). If symlinks are
+    /// disabled (see `symlink::is_disabled_error`), a regular file containing the target is written instead, like
+    /// Info-ZIP does. Nothing is followed, since `destination_path` was already checked by `create_parent_directory`.
+    pub fn create_symlink(&self, destination_path: &Path, target: &Path) -> anyhow::Result<()> {
+        match symlink::create_symlink(destination_path, target) {
+            Ok(()) => Ok(()),
+            Err(er) if symlink::is_disabled_error(&er) => {
+                tracing::debug!(
+                    entry_path = %self.get_relative_path(destination_path).display(),
+                    error = %er,
+                    "Could not create symlink, writing its target to a file instead"
+                );
+
+                fs::write(destination_path, target.as_os_str().as_encoded_bytes())?;
+
+                self.symlinks_written_as_files
+                    .set(self.symlinks_written_as_files.get().saturating_add(1_u64));
+
+                Ok(())
+            }
+            Err(er) => Err(anyhow::Error::from(er).context(format!(
+                "Could not create symlink \"{}\" -> \"{}\"",
+                destination_path.display(),
+                target.display()
+            ))),
+        }
+    }
+
+    /// Writes the contents of a file entry to `destination_path`, which must not exist. If "--sparse" was passed, runs
+    /// of zeros are skipped instead of written.
+    pub fn write_file(&self, destination_path: &Path, reader: &mut dyn Read) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(destination_path)?;
+
+        if self.extract_options.sparse {
+            let mut sparse_writer = SparseWriter::new(file);
+
+            io::copy(reader, &mut sparse_writer)?;
+
+            sparse_writer.finish()?;
+        } else {
+            io::copy(reader, &mut file)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the mode of an entry whose library does not set it (see `get_mode`)
+    pub fn set_mode(&self, destination_path: &Path, entry_info: &EntryInfo) -> anyhow::Result<()> {
+        // `fs::set_permissions` would follow a symlink, and a hard link shares the mode of its target
+        #[cfg(unix)]
+        if !matches!(
+            entry_info.kind,
+            EntryKind::HardLink(_) | EntryKind::Symlink(_)
+        ) {
+            use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+            if let Some(ut) = self.get_mode(entry_info) {
+                fs::set_permissions(destination_path, Permissions::from_mode(ut))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changing the owner of a file removes its setuid and setgid bits, so they are set again if "--keep-special-bits"
+    /// was passed
+    fn restore_special_bits(
+        &self,
+        destination_path: &Path,
+        entry_info: &EntryInfo,
+    ) -> anyhow::Result<()> {
+        #[cfg(unix)]
+        {
+            use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+            if matches!(entry_info.kind, EntryKind::Symlink(_)) {
+                return Ok(());
+            }
+
+            if let Some(ut) = self.get_mode(entry_info) {
+                if ut & SPECIAL_MODE_BITS != 0_u32 {
+                    fs::set_permissions(destination_path, Permissions::from_mode(ut))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the parent directories of `destination_path`, and makes sure that they do not resolve (e.g. via a
+    /// symlink extracted earlier) to a location outside of the new directory. This is checked before anything is
+    /// created, since `fs::create_dir_all` follows symlinks. During a dry run, nothing is created.
+    pub fn create_parent_directory(&self, destination_path: &Path) -> anyhow::Result<()> {
+        let Some(parent) = destination_path.parent() else {
+            return Ok(());
+        };
+
+        self.safe_path_resolver.ensure_inside(parent)?;
+
+        if self.extract_options.dry_run {
+            return Ok(());
+        }
+
+        self.new_directory.record_created_directories(parent);
+
+        fs::create_dir_all(parent)?;
+
+        self.safe_path_resolver.ensure_inside(parent)
+    }
+
+    /// Fails if `path` resolves to a location outside of the new directory. Used before the metadata of a directory is
+    /// set, since that follows symlinks.
+    pub fn ensure_inside_new_directory(&self, path: &Path) -> anyhow::Result<()> {
+        self.safe_path_resolver.ensure_inside(path)
+    }
+
+    /// Called after all entries have been processed. Prints the summary of a dry run, and moves the new directory
+    /// into place.
+    pub fn finish(self) -> anyhow::Result<()> {
+        for &(ref pa, en) in self.directory_times.borrow().iter() {
+            en.apply(pa.as_path())?;
+        }
+
+        let skipped_os_metadata = self.skipped_os_metadata.get();
+
+        if skipped_os_metadata != 0_u64 {
+            tracing::info!(
+                skipped_os_metadata,
+                "Skipped entries containing macOS or Windows metadata"
+            );
+        }
+
+        let skipped_special_files = self.skipped_special_files.get();
+
+        if skipped_special_files != 0_u64 {
+            tracing::warn!(skipped_special_files, "Skipped device nodes and FIFOs");
+        }
+
+        let stripped_special_bits = self.stripped_special_bits.get();
+
+        if stripped_special_bits != 0_u64 {
+            tracing::warn!(
+                stripped_special_bits,
+                "Removed setuid, setgid, and sticky bits"
+            );
+        }
+
+        let symlinks_written_as_files = self.symlinks_written_as_files.get();
+
+        if symlinks_written_as_files != 0_u64 {
+            tracing::warn!(
+                symlinks_written_as_files,
+                "Symlinks are not supported here, so they were written as files containing their targets"
+            );
+        }
+
+        self.ownership_tracker.report();
+
+        self.xattr_tracker.report();
+
+        let resumed_entries = self.resumed_entries.get();
+
+        if resumed_entries != 0_u64 {
+            tracing::info!(
+                resumed_entries,
+                "Skipped entries that were already extracted by the extraction being resumed"
+            );
+        }
+
+        if self.extract_options.dry_run {
+            self.dry_run_summary.print(self.new_directory.path())?;
+        }
+
+        self.extract_options.ensure_members_found()?;
+
+        if let Some(jo) = self.journal {
+            jo.remove()?;
+        }
+
+        if let Some(ref sy) = self.sync_tracker {
+            sy.sync_directories()?;
+        }
+
+        let is_staged = matches!(self.new_directory, NewDirectory::Staged(_));
+
+        let new_directory_path = self.new_directory.commit()?;
+
+        if let Some(sy) = self.sync_tracker {
+            if is_staged {
+                sy.sync_rename(new_directory_path.as_path())?;
+            }
+
+            sy.report();
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the entry is metadata created by macOS ("__MACOSX/", AppleDouble "._name" files, ".DS_Store") or Windows
+/// ("Thumbs.db", "desktop.ini")
+fn is_os_metadata(sanitized_entry_path: &Path) -> bool {
+    if sanitized_entry_path
+        .components()
+        .any(|co| co.as_os_str() == "__MACOSX")
+    {
+        return true;
+    }
+
+    sanitized_entry_path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .is_some_and(|st| {
+            st.starts_with("._")
+                || OS_METADATA_FILE_NAMES
+                    .iter()
+                    .any(|str| st.eq_ignore_ascii_case(str))
+        })
+}
+
+/// Whether two entry paths refer to the same entry (e.g. "./dir/file" and "dir/file")
+pub fn is_same_entry_path(entry_path: &Path, other_entry_path: &Path) -> bool {
+    match (
+        sanitize_entry_path(entry_path),
+        sanitize_entry_path(other_entry_path),
+    ) {
+        (Some(pa), Some(pat)) => pa == pat,
+        _ => false,
+    }
+}
+
+/// Whether a tar entry only contains metadata, which is not extracted. The tar crate applies PAX extended headers and
+/// GNU long names to the entry that follows them, but returns PAX global headers (e.g. the `pax_global_header` entry
+/// written by `git archive`) as entries of their own.
+pub fn is_tar_metadata_entry(entry_type: EntryType) -> bool {
+    entry_type.is_pax_global_extensions()
+        || entry_type.is_pax_local_extensions()
+        || entry_type.is_gnu_longname()
+        || entry_type.is_gnu_longlink()
+}
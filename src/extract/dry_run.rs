@@ -0,0 +1,160 @@
+use super::{EntryInfo, EntryKind};
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    path::Path,
+};
+
+/// Permission bits (including the setuid, setgid, and sticky bits) of a Unix mode
+const PERMISSION_BITS: u32 = 0o7777;
+
+/// Counts what would be done during a dry run
+#[derive(Default)]
+pub struct DryRunSummary {
+    bytes: Cell<u64>,
+    conflicts: Cell<u64>,
+    directories: Cell<u64>,
+    files: Cell<u64>,
+    links: Cell<u64>,
+    skipped: Cell<u64>,
+}
+
+impl DryRunSummary {
+    /// Reports that `action` (e.g. "create" or "overwrite") would be done for the entry. `mode` is the mode the entry
+    /// would be extracted with, and `existing_mode` is the mode of an existing directory that would be reused for a
+    /// directory entry.
+    pub fn report_planned(
+        &self,
+        action: &str,
+        destination_path: &Path,
+        entry_info: &EntryInfo,
+        mode: Option<u32>,
+        existing_mode: Option<u32>,
+    ) -> anyhow::Result<()> {
+        let mut details = Vec::<String>::with_capacity(2_usize);
+
+        let description = match entry_info.kind {
+            EntryKind::Directory => {
+                increment(&self.directories, 1_u64);
+
+                format!("directory \"{}\"", destination_path.display())
+            }
+            EntryKind::File => {
+                increment(&self.files, 1_u64);
+                increment(&self.bytes, entry_info.size);
+
+                details.push(format!("{} bytes", entry_info.size));
+
+                format!("file \"{}\"", destination_path.display())
+            }
+            EntryKind::HardLink(ref pa) => {
+                increment(&self.links, 1_u64);
+
+                format!(
+                    "hard link \"{}\" -> \"{}\"",
+                    destination_path.display(),
+                    pa.display()
+                )
+            }
+            EntryKind::Symlink(ref pa) => {
+                increment(&self.links, 1_u64);
+
+                format!(
+                    "symlink \"{}\" -> \"{}\"",
+                    destination_path.display(),
+                    pa.display()
+                )
+            }
+            EntryKind::Special(ref sp) => {
+                increment(&self.files, 1_u64);
+
+                format!(
+                    "{} \"{}\"",
+                    sp.kind.description(),
+                    destination_path.display()
+                )
+            }
+            EntryKind::Other => {
+                increment(&self.files, 1_u64);
+
+                format!(
+                    "file of unrecognized type \"{}\"",
+                    destination_path.display()
+                )
+            }
+        };
+
+        match (existing_mode, mode) {
+            (Some(ut), Some(uti)) if ut & PERMISSION_BITS != uti & PERMISSION_BITS => {
+                details.push(format!(
+                    "mode {:o} -> {:o}",
+                    ut & PERMISSION_BITS,
+                    uti & PERMISSION_BITS
+                ));
+            }
+            (_, Some(ut)) => {
+                details.push(format!("mode {:o}", ut & PERMISSION_BITS));
+            }
+            (_, None) => {}
+        }
+
+        if details.is_empty() {
+            writeln!(io::stdout().lock(), "{action} {description}")?;
+        } else {
+            writeln!(
+                io::stdout().lock(),
+                "{action} {description} ({})",
+                details.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports that the entry would not be extracted
+    pub fn report_skipped(&self, path: &Path, reason: &str) -> anyhow::Result<()> {
+        increment(&self.skipped, 1_u64);
+
+        writeln!(
+            io::stdout().lock(),
+            "skip \"{}\" ({reason})",
+            path.display()
+        )?;
+
+        Ok(())
+    }
+
+    /// Reports that the entry could not be extracted without user intervention (e.g. because the destination already
+    /// exists and no overwrite policy was chosen)
+    pub fn report_conflict(&self, destination_path: &Path, reason: &str) -> anyhow::Result<()> {
+        increment(&self.conflicts, 1_u64);
+
+        writeln!(
+            io::stdout().lock(),
+            "conflict \"{}\" ({reason})",
+            destination_path.display()
+        )?;
+
+        Ok(())
+    }
+
+    pub fn print(&self, new_directory: &Path) -> anyhow::Result<()> {
+        writeln!(
+            io::stdout().lock(),
+            "Dry run: would extract {} directories, {} files, and {} links ({} bytes) to \"{}\". {} entries skipped, {} conflicts.",
+            self.directories.get(),
+            self.files.get(),
+            self.links.get(),
+            self.bytes.get(),
+            new_directory.display(),
+            self.skipped.get(),
+            self.conflicts.get()
+        )?;
+
+        Ok(())
+    }
+}
+
+fn increment(cell: &Cell<u64>, amount: u64) {
+    cell.set(cell.get().saturating_add(amount));
+}
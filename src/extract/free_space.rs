@@ -0,0 +1,59 @@
+use std::path::Path;
+
+/// Fails if the file system containing `directory` does not have `required_bytes` of free space. The check is
+/// approximate, since file system overhead (e.g. partially used blocks) is not taken into account. Not implemented
+/// for non-Unix platforms.
+pub fn ensure_free_space(directory: &Path, required_bytes: u64) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        let available_bytes = get_available_bytes(directory)?;
+
+        anyhow::ensure!(
+            required_bytes <= available_bytes,
+            "Extracting the archive requires {required_bytes} bytes, but only {available_bytes} bytes are available on the file system containing \"{}\". Use \"--force\" to extract it anyway.",
+            directory.display()
+        );
+
+        tracing::debug!(available_bytes, required_bytes, "Enough free space");
+    }
+
+    #[cfg(not(unix))]
+    {
+        tracing::debug!(
+            directory = %directory.display(),
+            required_bytes,
+            "Checking free space is not implemented for this platform"
+        );
+    }
+
+    Ok(())
+}
+
+/// Bytes available to unprivileged users
+#[cfg(unix)]
+fn get_available_bytes(directory: &Path) -> anyhow::Result<u64> {
+    use std::{ffi::CString, io, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+
+    let directory_c_string = CString::new(directory.as_os_str().as_bytes())?;
+
+    let mut statvfs = MaybeUninit::<libc::statvfs>::uninit();
+
+    // Safety: `directory_c_string` is a valid C string, and `statvfs` points to memory of the right size
+    let result = unsafe { libc::statvfs(directory_c_string.as_ptr(), statvfs.as_mut_ptr()) };
+
+    if result != 0_i32 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    // Safety: `statvfs` succeeded, so it initialized the struct
+    let statvfs_initialized = unsafe { statvfs.assume_init() };
+
+    #[allow(
+        clippy::useless_conversion,
+        reason = "The types of these fields vary by platform"
+    )]
+    let available_bytes = u64::from(statvfs_initialized.f_bavail)
+        .saturating_mul(u64::from(statvfs_initialized.f_frsize));
+
+    Ok(available_bytes)
+}
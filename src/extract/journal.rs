@@ -0,0 +1,187 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Created in the directory entries are extracted to, and removed once every entry has been extracted
+pub const JOURNAL_FILE_NAME: &str = ".tarx-journal";
+
+const COMPLETED: &str = "completed";
+const STARTED: &str = "started";
+
+/// What the journal of an earlier extraction recorded about an entry
+pub enum JournalState {
+    /// The size of the extracted file, and the CRC-32 stored in the archive (both 0 for directories and links, and the
+    /// CRC-32 is 0 if the archive does not store one)
+    Completed { crc32: u32, size: u64 },
+    /// The entry was being extracted when the earlier extraction stopped, so it may be incomplete
+    Started,
+}
+
+/// Records which entries have been extracted, so that an extraction that did not finish can be continued with
+/// "--resume". Each line is either "started PATH" or "completed SIZE CRC32 PATH", where `PATH` is the hex-encoded path
+/// of the entry relative to the directory.
+pub struct Journal {
+    directory: PathBuf,
+    earlier_states: HashMap<String, JournalState>,
+    file: RefCell<File>,
+    path: PathBuf,
+}
+
+impl Journal {
+    /// Creates the journal in `directory`. If `resume` is set, the states recorded by an existing journal are loaded,
+    /// and new states are appended to it.
+    pub fn open(directory: &Path, resume: bool) -> anyhow::Result<Journal> {
+        let path = directory.join(JOURNAL_FILE_NAME);
+
+        let earlier_states = if resume {
+            load_states(path.as_path())?
+        } else {
+            HashMap::new()
+        };
+
+        let file = if resume {
+            OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path.as_path())?
+        } else {
+            File::create(path.as_path())?
+        };
+
+        Ok(Journal {
+            directory: directory.to_path_buf(),
+            earlier_states,
+            file: RefCell::new(file),
+            path,
+        })
+    }
+
+    pub fn get_earlier_state(&self, destination_path: &Path) -> Option<&JournalState> {
+        self.earlier_states.get(&self.encode_path(destination_path))
+    }
+
+    /// Total size of the entries completed by the extraction being resumed
+    pub fn get_earlier_completed_size(&self) -> u64 {
+        self.earlier_states
+            .values()
+            .fold(0_u64, |acc, jo| match *jo {
+                JournalState::Completed { size, .. } => acc.saturating_add(size),
+                JournalState::Started => acc,
+            })
+    }
+
+    pub fn record_started(&self, destination_path: &Path) -> anyhow::Result<()> {
+        let encoded_path = self.encode_path(destination_path);
+
+        self.write_line(format!("{STARTED} {encoded_path}").as_str())
+    }
+
+    pub fn record_completed(
+        &self,
+        destination_path: &Path,
+        size: u64,
+        crc32: u32,
+    ) -> anyhow::Result<()> {
+        let encoded_path = self.encode_path(destination_path);
+
+        self.write_line(format!("{COMPLETED} {size} {crc32:08x} {encoded_path}").as_str())
+    }
+
+    /// Called once every entry has been extracted
+    pub fn remove(self) -> anyhow::Result<()> {
+        drop(self.file);
+
+        fs::remove_file(self.path.as_path())?;
+
+        Ok(())
+    }
+
+    fn encode_path(&self, destination_path: &Path) -> String {
+        let relative_path = destination_path
+            .strip_prefix(self.directory.as_path())
+            .unwrap_or(destination_path);
+
+        hex::encode(relative_path.as_os_str().as_encoded_bytes())
+    }
+
+    /// Not buffered, so that every line is in the file if the process is killed
+    fn write_line(&self, line: &str) -> anyhow::Result<()> {
+        self.file
+            .borrow_mut()
+            .write_all(format!("{line}\n").as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Later lines take precedence. Lines that cannot be parsed (e.g. a line that was only partially written when the
+/// process was killed) are ignored.
+fn load_states(path: &Path) -> anyhow::Result<HashMap<String, JournalState>> {
+    let file = match File::open(path) {
+        Ok(fi) => fi,
+        Err(er) if er.kind() == ErrorKind::NotFound => {
+            return Ok(HashMap::new());
+        }
+        Err(er) => {
+            return Err(er.into());
+        }
+    };
+
+    let mut states = HashMap::new();
+
+    for re in BufReader::new(file).lines() {
+        let line = re?;
+
+        let mut split = line.split(' ');
+
+        let parsed_option = match (split.next(), split.next(), split.next(), split.next()) {
+            (Some(STARTED), Some(st), None, None) => Some((st, JournalState::Started)),
+            (Some(COMPLETED), Some(st), Some(str), Some(stri)) => {
+                match (st.parse::<u64>(), u32::from_str_radix(str, 16_u32)) {
+                    (Ok(size), Ok(crc32)) => Some((stri, JournalState::Completed { crc32, size })),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        let Some((encoded_path, state)) = parsed_option else {
+            tracing::debug!(line, "Ignoring invalid line in journal");
+
+            continue;
+        };
+
+        states.insert(encoded_path.to_owned(), state);
+    }
+
+    Ok(states)
+}
+
+/// Computes the CRC-32 of a partially extracted file, to check whether it was actually complete
+pub fn compute_file_crc32(path: &Path) -> anyhow::Result<u32> {
+    let mut file = File::open(path)?;
+
+    let mut hasher = crc32fast::Hasher::new();
+
+    let mut buffer = vec![0_u8; 65_536_usize];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+
+        let Some(sl) = buffer.get(..read) else {
+            anyhow::bail!("Read more bytes than the size of the buffer");
+        };
+
+        if sl.is_empty() {
+            break;
+        }
+
+        hasher.update(sl);
+    }
+
+    Ok(hasher.finalize())
+}
@@ -0,0 +1,226 @@
+use anyhow::Context;
+use std::{
+    cell::Cell,
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+/// Limits that protect against decompression bombs. `None` means unlimited.
+#[derive(Default)]
+pub struct Limits {
+    /// Size of the archive file, which `max_ratio` is relative to
+    pub archive_size: u64,
+    /// Maximum number of components in the path of an entry
+    pub max_path_components: Option<usize>,
+    pub max_entries: Option<u64>,
+    pub max_entry_size: Option<u64>,
+    /// Maximum total size of the extracted entries, as a multiple of `archive_size`
+    pub max_ratio: Option<u64>,
+    pub max_total_size: Option<u64>,
+}
+
+impl Limits {
+    /// The lower of `max_total_size` and `max_ratio` times the size of the archive file, and the option that sets it
+    pub fn get_max_total_size(&self) -> Option<(u64, &'static str)> {
+        let max_ratio_size = self
+            .max_ratio
+            .map(|ut| (ut.saturating_mul(self.archive_size), "--max-ratio"));
+
+        let max_total_size = self.max_total_size.map(|ut| (ut, "--max-total-size"));
+
+        match (max_total_size, max_ratio_size) {
+            (Some(tu), Some(tup)) => Some(if tup.0 < tu.0 { tup } else { tu }),
+            (tu, tup) => tu.or(tup),
+        }
+    }
+
+    /// Limits the output of a decompressor that decompresses the whole archive into memory (see `LimitedWriter`)
+    pub fn get_max_decompressed_size(&self) -> Option<u64> {
+        self.get_max_total_size().map(|(ut, _)| ut)
+    }
+}
+
+/// Keeps track of the limits while an archive is extracted
+pub struct LimitTracker<'a> {
+    entries: Cell<u64>,
+    limits: &'a Limits,
+    total_size: Cell<u64>,
+}
+
+impl LimitTracker<'_> {
+    pub fn new(limits: &Limits) -> LimitTracker<'_> {
+        LimitTracker {
+            entries: Cell::new(0_u64),
+            limits,
+            total_size: Cell::new(0_u64),
+        }
+    }
+
+    /// Called for every entry of the archive, including entries that are not extracted and metadata entries of tar
+    /// archives
+    pub fn check_entry(&self, entry_path: &Path) -> anyhow::Result<()> {
+        let entries = self.entries.get().saturating_add(1_u64);
+
+        self.entries.set(entries);
+
+        if let Some(ut) = self.limits.max_entries {
+            anyhow::ensure!(
+                entries <= ut,
+                "Archive has more than {ut} entries (\"--max-entries\"), stopped at \"{}\"",
+                entry_path.display()
+            );
+        }
+
+        if let Some(us) = self.limits.max_path_components {
+            let components = entry_path
+                .components()
+                .filter(|co| matches!(*co, Component::Normal(_)))
+                .count();
+
+            anyhow::ensure!(
+                components <= us,
+                "Path of \"{}\" has {components} components, more than the maximum of {us} (\"--max-path-components\")",
+                entry_path.display()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Called before an entry is extracted, with the uncompressed size stored in the archive. Entries whose actual
+    /// size can differ from it must be read through a `LimitedReader`.
+    pub fn add_entry_size(&self, entry_path: &Path, size: u64) -> anyhow::Result<()> {
+        if let Some(ut) = self.limits.max_entry_size {
+            anyhow::ensure!(
+                size <= ut,
+                "\"{}\" is {size} bytes, more than the maximum of {ut} bytes (\"--max-entry-size\")",
+                entry_path.display()
+            );
+        }
+
+        let total_size = self.total_size.get().saturating_add(size);
+
+        self.total_size.set(total_size);
+
+        if let Some((ut, option)) = self.limits.get_max_total_size() {
+            anyhow::ensure!(
+                total_size <= ut,
+                "Extracting \"{}\" would make the total size of the extracted entries more than {ut} bytes (\"{option}\")",
+                entry_path.display()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Fails if more than `size` bytes are read, for formats where the size stored in the archive is not what limits
+/// how much data is decompressed
+pub struct LimitedReader<R> {
+    entry_path: PathBuf,
+    read: u64,
+    reader: R,
+    size: u64,
+}
+
+impl<R> LimitedReader<R> {
+    pub fn new(reader: R, size: u64, entry_path: &Path) -> LimitedReader<R> {
+        LimitedReader {
+            entry_path: entry_path.to_path_buf(),
+            read: 0_u64,
+            reader,
+            size,
+        }
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+
+        self.read = self
+            .read
+            .saturating_add(u64::try_from(read).map_err(io::Error::other)?);
+
+        if self.read > self.size {
+            return Err(io::Error::other(format!(
+                "\"{}\" contains more data than the {} bytes stored in the archive",
+                self.entry_path.display(),
+                self.size
+            )));
+        }
+
+        Ok(read)
+    }
+}
+
+/// Fails once more than `max_size` bytes have been written. Used when the whole archive is decompressed into memory
+/// before it is extracted, in which case the entry that exceeds the limit is not known.
+pub struct LimitedWriter<W> {
+    max_size: u64,
+    writer: W,
+    written: u64,
+}
+
+impl<W> LimitedWriter<W> {
+    pub fn new(writer: W, max_size: Option<u64>) -> LimitedWriter<W> {
+        LimitedWriter {
+            max_size: max_size.unwrap_or(u64::MAX),
+            writer,
+            written: 0_u64,
+        }
+    }
+}
+
+impl<W: Write> Write for LimitedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self
+            .written
+            .saturating_add(u64::try_from(buf.len()).map_err(io::Error::other)?);
+
+        if written > self.max_size {
+            return Err(io::Error::other(format!(
+                "Decompressed archive is more than {} bytes (\"--max-total-size\" or \"--max-ratio\")",
+                self.max_size
+            )));
+        }
+
+        self.written = written;
+
+        self.writer.write_all(buf)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Parses a number of bytes, optionally followed by "K", "M", "G", or "T" (powers of 1,024)
+pub fn parse_size(size: &str) -> anyhow::Result<u64> {
+    let (number, multiplier) = match size.char_indices().last() {
+        Some((us, ch)) if ch.is_ascii_alphabetic() => {
+            let multiplier = match ch.to_ascii_uppercase() {
+                'K' => 1_u64 << 10_u32,
+                'M' => 1_u64 << 20_u32,
+                'G' => 1_u64 << 30_u32,
+                'T' => 1_u64 << 40_u32,
+                _ => {
+                    anyhow::bail!(
+                        "Unrecognized suffix \"{ch}\" in \"{size}\". Supported suffixes are \"K\", \"M\", \"G\", and \"T\"."
+                    );
+                }
+            };
+
+            (size.get(..us).unwrap_or_default(), multiplier)
+        }
+        _ => (size, 1_u64),
+    };
+
+    number
+        .parse::<u64>()
+        .with_context(|| format!("\"{size}\" is not a valid size"))?
+        .checked_mul(multiplier)
+        .with_context(|| format!("\"{size}\" is too large"))
+}
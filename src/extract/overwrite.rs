@@ -0,0 +1,136 @@
+use crate::{interrupt, new_directory::NewDirectory};
+use anyhow::Context;
+use std::{
+    fs::{self, Metadata},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// What to do when the destination of an entry already exists
+#[derive(Clone, Copy)]
+pub enum OverwritePolicy {
+    /// Ask what to do for each existing file
+    Ask,
+    /// Fail
+    Error,
+    /// Replace the existing file if it is older than the entry, otherwise keep it
+    KeepNewer,
+    /// Replace the existing file
+    Overwrite,
+    /// Extract the entry under a new name (e.g. "name (1).txt")
+    Rename,
+    /// Keep the existing file, and do not extract the entry
+    SkipExisting,
+}
+
+/// What is actually done with an existing file
+#[derive(Clone, Copy)]
+pub enum ExistingAction {
+    Overwrite,
+    Rename,
+    Skip,
+}
+
+impl OverwritePolicy {
+    /// `ask` is only called for `OverwritePolicy::Ask`
+    pub fn get_existing_action(
+        self,
+        destination_path: &Path,
+        metadata: &Metadata,
+        modification_time: Option<SystemTime>,
+        ask: impl FnOnce() -> anyhow::Result<ExistingAction>,
+    ) -> anyhow::Result<ExistingAction> {
+        let existing_action = match self {
+            OverwritePolicy::Ask => ask()?,
+            OverwritePolicy::Error => {
+                anyhow::bail!(
+                    "\"{}\" already exists. Use \"--overwrite\", \"--skip-existing\", \"--keep-newer\", \"--rename\", or \"--interactive\"/\"-i\" to choose what happens to existing files.",
+                    destination_path.display()
+                );
+            }
+            OverwritePolicy::KeepNewer => match (metadata.modified(), modification_time) {
+                (Ok(sy), Some(sys)) if sy < sys => ExistingAction::Overwrite,
+                // The existing file is at least as new as the entry, or one of the modification times is not known
+                _ => ExistingAction::Skip,
+            },
+            OverwritePolicy::Overwrite => ExistingAction::Overwrite,
+            OverwritePolicy::Rename => ExistingAction::Rename,
+            OverwritePolicy::SkipExisting => ExistingAction::Skip,
+        };
+
+        Ok(existing_action)
+    }
+}
+
+/// Asks the user what to do with an existing file. The second element of the returned tuple is `true` if the same
+/// should be done for all remaining existing files.
+pub fn ask_existing_action(destination_path: &Path) -> anyhow::Result<(ExistingAction, bool)> {
+    let mut stdout_lock = io::stdout().lock();
+
+    loop {
+        write!(
+            &mut stdout_lock,
+            "\"{}\" already exists. Overwrite it? [y]es, [n]o, [r]ename, [A]ll, [N]one: ",
+            destination_path.display()
+        )?;
+
+        stdout_lock.flush()?;
+
+        let read_line = interrupt::read_line()?.with_context(|| {
+            format!(
+                "Reached the end of standard input while asking what to do with \"{}\"",
+                destination_path.display()
+            )
+        })?;
+
+        match read_line.as_str() {
+            "y" => return Ok((ExistingAction::Overwrite, false)),
+            "n" => return Ok((ExistingAction::Skip, false)),
+            "r" => return Ok((ExistingAction::Rename, false)),
+            "A" => return Ok((ExistingAction::Overwrite, true)),
+            "N" => return Ok((ExistingAction::Skip, true)),
+            _ => {}
+        }
+    }
+}
+
+/// Removes an existing file or symlink, so that it can be replaced (see `NewDirectory::remove_existing`). Directories
+/// are not removed.
+pub fn remove_existing(
+    destination_path: &Path,
+    metadata: &Metadata,
+    new_directory: &NewDirectory,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !metadata.is_dir(),
+        "\"{}\" is an existing directory, and cannot be overwritten",
+        destination_path.display()
+    );
+
+    new_directory.remove_existing(destination_path)
+}
+
+/// Appends " (1)", " (2)", etc. to the file stem of `path` until a path that does not exist is found. The name is not
+/// required to be valid UTF-8.
+pub fn find_unique_path(path: &Path) -> anyhow::Result<PathBuf> {
+    let file_stem = path.file_stem().context("Could not get file stem")?;
+
+    let extension = path.extension();
+
+    (1_u32..=u32::MAX)
+        .map(|ut| {
+            let mut file_name = file_stem.to_os_string();
+
+            file_name.push(format!(" ({ut})"));
+
+            if let Some(os) = extension {
+                file_name.push(".");
+                file_name.push(os);
+            }
+
+            path.with_file_name(file_name)
+        })
+        .find(|pa| fs::symlink_metadata(pa).is_err())
+        .context("Could not find a unique path")
+}
@@ -0,0 +1,261 @@
+use anyhow::Context;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// How the owner and group of extracted entries are set
+#[derive(Default)]
+pub struct OwnershipOptions {
+    /// Group ID set on every entry ("--group")
+    pub group: Option<u32>,
+    /// Maps group IDs stored in the archive to the group IDs that are set ("--group-map")
+    pub group_map: HashMap<u32, u32>,
+    /// User ID set on every entry ("--owner")
+    pub owner: Option<u32>,
+    /// Maps user IDs stored in the archive to the user IDs that are set ("--owner-map")
+    pub owner_map: HashMap<u32, u32>,
+    /// Set the owner and group stored in the archive. The default when running as root.
+    pub same_owner: bool,
+}
+
+impl OwnershipOptions {
+    /// The user and group IDs to set on an entry, given the IDs stored in the archive (if any). `None` means that the
+    /// ID is not changed.
+    fn get_ids(
+        &self,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        tracker: &OwnershipTracker,
+    ) -> (Option<u32>, Option<u32>) {
+        let get_id = |override_id: Option<u32>,
+                      archive_id: Option<u32>,
+                      map: &HashMap<u32, u32>,
+                      unmapped: &RefCell<BTreeSet<u32>>| {
+            if override_id.is_some() {
+                return override_id;
+            }
+
+            if !self.same_owner {
+                return None;
+            }
+
+            let id = archive_id?;
+
+            if map.is_empty() {
+                return Some(id);
+            }
+
+            // IDs that are not in the map are kept, and reported at the end
+            Some(map.get(&id).copied().unwrap_or_else(|| {
+                unmapped.borrow_mut().insert(id);
+
+                id
+            }))
+        };
+
+        (
+            get_id(self.owner, uid, &self.owner_map, &tracker.unmapped_uids),
+            get_id(self.group, gid, &self.group_map, &tracker.unmapped_gids),
+        )
+    }
+}
+
+/// Collects what could not be done while setting owners, so that it can be reported once at the end instead of for
+/// every entry
+#[derive(Default)]
+pub struct OwnershipTracker {
+    failed: Cell<u64>,
+    first_failure: RefCell<Option<(PathBuf, String)>>,
+    unmapped_gids: RefCell<BTreeSet<u32>>,
+    unmapped_uids: RefCell<BTreeSet<u32>>,
+}
+
+impl OwnershipTracker {
+    /// Sets the owner and group of `path` (without following symlinks). Returns whether anything was changed. Failures
+    /// are recorded (with `entry_path`) instead of returned, so that the rest of the archive is still extracted.
+    pub fn apply(
+        &self,
+        ownership_options: &OwnershipOptions,
+        path: &Path,
+        entry_path: &Path,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> bool {
+        let (uid_to_set, gid_to_set) = ownership_options.get_ids(uid, gid, self);
+
+        if uid_to_set.is_none() && gid_to_set.is_none() {
+            return false;
+        }
+
+        if let Err(er) = lchown(path, uid_to_set, gid_to_set) {
+            self.failed.set(self.failed.get().saturating_add(1_u64));
+
+            self.first_failure
+                .borrow_mut()
+                .get_or_insert_with(|| (entry_path.to_path_buf(), er.to_string()));
+
+            return false;
+        }
+
+        true
+    }
+
+    /// Called once every entry has been extracted
+    pub fn report(&self) {
+        let failed = self.failed.get();
+
+        if let Some((ref pa, ref st)) = *self.first_failure.borrow() {
+            tracing::warn!(
+                failed,
+                first_entry_path = %pa.display(),
+                first_error = st,
+                "Could not set the owner or group of some entries"
+            );
+        }
+
+        for (ids, map_option) in [
+            (&self.unmapped_uids, "--owner-map"),
+            (&self.unmapped_gids, "--group-map"),
+        ] {
+            let ids_borrow = ids.borrow();
+
+            if !ids_borrow.is_empty() {
+                let ids_string = ids_borrow
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                tracing::warn!(
+                    ids = ids_string,
+                    "IDs stored in the archive are not in the map passed to \"{map_option}\", so they were kept"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lchown(path: &Path, uid: Option<u32>, gid: Option<u32>) -> anyhow::Result<()> {
+    use std::os::unix::fs as unix_fs;
+
+    unix_fs::lchown(path, uid, gid)?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn lchown(path: &Path, _: Option<u32>, _: Option<u32>) -> anyhow::Result<()> {
+    anyhow::bail!(
+        "Setting the owner of \"{}\" is not implemented for this platform",
+        path.display()
+    );
+}
+
+/// Whether tarx is running as root, in which case the owners stored in the archive are set by default (like tar)
+pub fn is_root() -> bool {
+    #[cfg(unix)]
+    {
+        // Safety: `geteuid` cannot fail, and has no side effects
+        unsafe { libc::geteuid() == 0 }
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Parses a user name or numeric user ID
+pub fn parse_user(user: &str) -> anyhow::Result<u32> {
+    if let Ok(ut) = user.parse::<u32>() {
+        return Ok(ut);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::ffi::CString;
+
+        let user_c_string = CString::new(user)?;
+
+        // Safety: `user_c_string` is a valid C string. The returned struct is only read before any other call that
+        // could overwrite it.
+        let passwd = unsafe { libc::getpwnam(user_c_string.as_ptr()) };
+
+        // Safety: `getpwnam` returns either a null pointer or a pointer to a valid struct
+        if let Some(pa) = unsafe { passwd.as_ref() } {
+            return Ok(pa.pw_uid);
+        }
+    }
+
+    anyhow::bail!("\"{user}\" is not a user ID or the name of an existing user");
+}
+
+/// Parses a group name or numeric group ID
+pub fn parse_group(group: &str) -> anyhow::Result<u32> {
+    if let Ok(ut) = group.parse::<u32>() {
+        return Ok(ut);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::ffi::CString;
+
+        let group_c_string = CString::new(group)?;
+
+        // Safety: `group_c_string` is a valid C string. The returned struct is only read before any other call that
+        // could overwrite it.
+        let group_struct = unsafe { libc::getgrnam(group_c_string.as_ptr()) };
+
+        // Safety: `getgrnam` returns either a null pointer or a pointer to a valid struct
+        if let Some(gr) = unsafe { group_struct.as_ref() } {
+            return Ok(gr.gr_gid);
+        }
+    }
+
+    anyhow::bail!("\"{group}\" is not a group ID or the name of an existing group");
+}
+
+/// Reads a map of numeric IDs. Each line contains an ID stored in the archive and the ID to set instead, separated by
+/// whitespace (e.g. "1000 0"). Empty lines and lines starting with "#" are ignored.
+pub fn read_id_map(path: &Path) -> anyhow::Result<HashMap<u32, u32>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Could not read ID map \"{}\"", path.display()))?;
+
+    let mut id_map = HashMap::new();
+
+    for (us, st) in contents.lines().enumerate() {
+        let line = st.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut split = line.split_whitespace();
+
+        let (Some(str), Some(stri), None) = (split.next(), split.next(), split.next()) else {
+            anyhow::bail!(
+                "Line {} of \"{}\" is not of the form \"OLD_ID NEW_ID\"",
+                us.saturating_add(1_usize),
+                path.display()
+            );
+        };
+
+        let parse = |id: &str| {
+            id.parse::<u32>().with_context(|| {
+                format!(
+                    "\"{id}\" on line {} of \"{}\" is not a numeric ID",
+                    us.saturating_add(1_usize),
+                    path.display()
+                )
+            })
+        };
+
+        id_map.insert(parse(str)?, parse(stri)?);
+    }
+
+    Ok(id_map)
+}
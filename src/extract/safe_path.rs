@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Every entry of every format is written to a path resolved by this, so that nothing is written outside of the new
+/// directory. Entry paths that are absolute or contain ".." components are rejected (see `sanitize_entry_path`), and
+/// so are paths whose existing ancestors resolve outside of the new directory (e.g. through a symlink extracted earlier
+/// from the same archive).
+pub struct SafePathResolver {
+    new_directory: PathBuf,
+    new_directory_canonical: PathBuf,
+}
+
+impl SafePathResolver {
+    /// During a dry run, the new directory does not exist
+    pub fn new(new_directory: &Path, dry_run: bool) -> anyhow::Result<SafePathResolver> {
+        let new_directory_canonical = if dry_run {
+            canonicalize_existing_ancestor(new_directory)?
+        } else {
+            fs::canonicalize(new_directory)?
+        };
+
+        Ok(SafePathResolver {
+            new_directory: new_directory.to_path_buf(),
+            new_directory_canonical,
+        })
+    }
+
+    /// Joins a sanitized relative path to the new directory. Returns `None` for an empty path.
+    pub fn resolve(&self, sanitized_relative_path: &Path) -> Option<PathBuf> {
+        if sanitized_relative_path.as_os_str().is_empty() {
+            return None;
+        }
+
+        Some(self.new_directory.join(sanitized_relative_path))
+    }
+
+    /// Fails if `path`, or the nearest ancestor of it that exists, resolves to a location outside of the new
+    /// directory. Called before directories are created or entries are written, since both follow symlinks.
+    pub fn ensure_inside(&self, path: &Path) -> anyhow::Result<()> {
+        let path_canonical = canonicalize_existing_ancestor(path)?;
+
+        anyhow::ensure!(
+            path_canonical.starts_with(self.new_directory_canonical.as_path()),
+            "\"{}\" resolves to \"{}\", which is outside of the new directory",
+            path.display(),
+            path_canonical.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Converts an entry path into a relative path. "." components are ignored, and paths that are absolute, contain ".."
+/// components, or contain NUL bytes are rejected.
+pub fn sanitize_entry_path(entry_path: &Path) -> Option<PathBuf> {
+    let mut path_buf = PathBuf::new();
+
+    for co in entry_path.components() {
+        match co {
+            // Such paths cannot be created
+            Component::Normal(os) if os.as_encoded_bytes().contains(&0_u8) => {
+                return None;
+            }
+            Component::Normal(os) => {
+                path_buf.push(os);
+            }
+            Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) | Component::RootDir => {
+                return None;
+            }
+        }
+    }
+
+    Some(path_buf)
+}
+
+/// Canonicalizes the nearest existing ancestor of `path`, and appends the rest of `path` to it. Fails if that ancestor
+/// is a symlink whose target does not exist.
+pub fn canonicalize_existing_ancestor(path: &Path) -> anyhow::Result<PathBuf> {
+    for pa in path.ancestors() {
+        if fs::symlink_metadata(pa).is_ok() {
+            let rest = path.strip_prefix(pa)?;
+
+            return Ok(fs::canonicalize(pa)?.join(rest));
+        }
+    }
+
+    anyhow::bail!(
+        "Could not find an existing ancestor of \"{}\"",
+        path.display()
+    );
+}
@@ -0,0 +1,598 @@
+use anyhow::Context;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    iter,
+    path::PathBuf,
+};
+use tar::Entry;
+
+/// Runs of zeros are only skipped in whole chunks of this size, which is the block size of most file systems
+const HOLE_CHUNK_SIZE: usize = 4_096;
+const HOLE_CHUNK_SIZE_U64: u64 = 4_096;
+
+/// The sparse map that version 1.0 stores before the data is padded to a multiple of the tar block size
+const TAR_BLOCK_SIZE: u64 = 512;
+
+/// Longest line of the sparse map that version 1.0 stores before the data. Each line contains a single decimal number.
+const MAX_MAP_LINE_LENGTH: u64 = 32;
+
+/// A writer that can skip a run of zeros instead of writing it
+pub trait WriteHole: Write {
+    fn write_hole(&mut self, size: u64) -> io::Result<()> {
+        io::copy(&mut io::repeat(0_u8).take(size), self)?;
+
+        Ok(())
+    }
+}
+
+impl<W: Write> WriteHole for BufWriter<W> {}
+
+/// Writes a file, seeking over runs of zeros instead of writing them, so that they become holes on file systems that
+/// support sparse files. `finish` has to be called once everything has been written.
+pub struct SparseWriter {
+    file: File,
+    /// Zeros that have not been skipped yet
+    pending_hole: u64,
+    /// Position in the file, including `pending_hole`
+    position: u64,
+}
+
+impl SparseWriter {
+    pub fn new(file: File) -> SparseWriter {
+        SparseWriter {
+            file,
+            pending_hole: 0_u64,
+            position: 0_u64,
+        }
+    }
+
+    fn skip_pending_hole(&mut self) -> io::Result<()> {
+        if self.pending_hole != 0_u64 {
+            self.file.seek(SeekFrom::Current(
+                i64::try_from(self.pending_hole).map_err(io::Error::other)?,
+            ))?;
+
+            self.pending_hole = 0_u64;
+        }
+
+        Ok(())
+    }
+
+    /// A hole at the end of the file is only created here, by setting the length of the file
+    pub fn finish(mut self) -> io::Result<File> {
+        if self.pending_hole != 0_u64 {
+            self.skip_pending_hole()?;
+
+            self.file.set_len(self.position)?;
+        }
+
+        Ok(self.file)
+    }
+}
+
+impl Write for SparseWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Chunks are aligned to the file, so that data and zeros do not share a block. Decompressors often return
+        // fewer bytes than requested, so runs of zeros shorter than a chunk are skipped too.
+        let offset_in_chunk =
+            usize::try_from(self.position % HOLE_CHUNK_SIZE_U64).map_err(io::Error::other)?;
+
+        let (first_chunk, rest) = buf.split_at(
+            HOLE_CHUNK_SIZE
+                .saturating_sub(offset_in_chunk)
+                .min(buf.len()),
+        );
+
+        let is_hole = |chunk: &[u8]| chunk.iter().all(|&ut| ut == 0_u8);
+
+        let first_is_hole = is_hole(first_chunk);
+
+        // The longest prefix of chunks that are all zeros, or all contain data
+        let length = iter::once(first_chunk)
+            .chain(rest.chunks(HOLE_CHUNK_SIZE))
+            .take_while(|sl| is_hole(sl) == first_is_hole)
+            .map(<[u8]>::len)
+            .sum::<usize>();
+
+        let written = if first_is_hole {
+            self.pending_hole = self
+                .pending_hole
+                .saturating_add(u64::try_from(length).map_err(io::Error::other)?);
+
+            length
+        } else {
+            self.skip_pending_hole()?;
+
+            self.file.write(buf.get(..length).unwrap_or(buf))?
+        };
+
+        self.position = self
+            .position
+            .saturating_add(u64::try_from(written).map_err(io::Error::other)?);
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl WriteHole for SparseWriter {
+    fn write_hole(&mut self, size: u64) -> io::Result<()> {
+        self.pending_hole = self.pending_hole.saturating_add(size);
+
+        self.position = self.position.saturating_add(size);
+
+        Ok(())
+    }
+}
+
+/// A sparse file stored in one of the PAX formats of GNU tar (versions 0.0, 0.1, and 1.0), which `tar::Entry::unpack`
+/// does not expand. The old GNU format is expanded by `tar::Entry::unpack` itself.
+pub struct PaxSparseFile {
+    /// Offsets and sizes of the regions that contain data, in order. Version 1.0 stores these before the data instead.
+    map: Option<Vec<(u64, u64)>>,
+    /// The original path. The path in the header is made up (e.g. "./GNUSparseFile.1234/file.img").
+    name: Option<PathBuf>,
+    /// Size of the expanded file
+    real_size: u64,
+}
+
+impl PaxSparseFile {
+    /// Reads the PAX extended header of `entry`. Returns `None` if the entry is not a sparse file.
+    pub fn from_entry<R: Read>(entry: &mut Entry<R>) -> anyhow::Result<Option<PaxSparseFile>> {
+        let Some(pax_extensions) = entry.pax_extensions()? else {
+            return Ok(None);
+        };
+
+        let mut major = None;
+        let mut name = None;
+        let mut real_size = None;
+        let mut map_string = None;
+        // Version 0.0 repeats these keys for every region
+        let mut offsets = Vec::new();
+        let mut sizes = Vec::new();
+
+        for re in pax_extensions {
+            let pax_extension = re?;
+
+            let Ok(key) = pax_extension.key() else {
+                continue;
+            };
+
+            if !key.starts_with("GNU.sparse.") {
+                continue;
+            }
+
+            let value = pax_extension
+                .value()
+                .with_context(|| format!("Value of PAX record \"{key}\" is not valid UTF-8"))?;
+
+            let parse = |st: &str| {
+                st.parse::<u64>().with_context(|| {
+                    format!("Value \"{st}\" of PAX record \"{key}\" is not a number")
+                })
+            };
+
+            match key {
+                "GNU.sparse.major" => major = Some(parse(value)?),
+                "GNU.sparse.name" => name = Some(PathBuf::from(value)),
+                "GNU.sparse.realsize" | "GNU.sparse.size" => real_size = Some(parse(value)?),
+                "GNU.sparse.map" => map_string = Some(value.to_owned()),
+                "GNU.sparse.offset" => offsets.push(parse(value)?),
+                "GNU.sparse.numbytes" => sizes.push(parse(value)?),
+                _ => {}
+            }
+        }
+
+        let entry_path = entry.path()?.into_owned();
+
+        let map = if major == Some(1_u64) {
+            None
+        } else if let Some(st) = map_string {
+            let mut numbers = st.split(',').filter(|str| !str.is_empty()).map(|str| {
+                str.parse::<u64>().with_context(|| {
+                    format!("Sparse map \"{st}\" contains \"{str}\", which is not a number")
+                })
+            });
+
+            let mut map = Vec::new();
+
+            while let Some(re) = numbers.next() {
+                let size = numbers.next().with_context(|| {
+                    format!("Sparse map \"{st}\" does not contain pairs of offsets and sizes")
+                })??;
+
+                map.push((re?, size));
+            }
+
+            Some(map)
+        } else if major.is_none() && real_size.is_none() {
+            return Ok(None);
+        } else {
+            // Version 0.0 stores no regions for a file that only contains zeros
+            anyhow::ensure!(
+                offsets.len() == sizes.len(),
+                "Sparse map of \"{}\" has {} offsets but {} sizes",
+                entry_path.display(),
+                offsets.len(),
+                sizes.len()
+            );
+
+            Some(offsets.into_iter().zip(sizes).collect())
+        };
+
+        Ok(Some(PaxSparseFile {
+            map,
+            name,
+            real_size: real_size.with_context(|| {
+                format!(
+                    "Size of sparse file \"{}\" is not stored",
+                    entry_path.display()
+                )
+            })?,
+        }))
+    }
+
+    pub fn get_real_size(&self) -> u64 {
+        self.real_size
+    }
+}
+
+/// The path of a tar entry, which is the original path for sparse files (see `PaxSparseFile`)
+pub fn get_tar_entry_path<R: Read>(
+    entry: &Entry<R>,
+    pax_sparse_file: Option<&PaxSparseFile>,
+) -> anyhow::Result<PathBuf> {
+    if let Some(pa) = pax_sparse_file.and_then(|pa| pa.name.as_ref()) {
+        return Ok(pa.clone());
+    }
+
+    Ok(entry.path()?.into_owned())
+}
+
+/// Copies the expanded contents of a sparse file from its tar entry to `writer`
+pub fn copy_pax_sparse_file(
+    reader: impl Read,
+    writer: &mut impl WriteHole,
+    pax_sparse_file: &PaxSparseFile,
+) -> anyhow::Result<()> {
+    let mut buf_reader = BufReader::new(reader);
+
+    let map = match pax_sparse_file.map {
+        Some(ref ve) => Cow::Borrowed(ve),
+        None => Cow::Owned(read_sparse_map(&mut buf_reader)?),
+    };
+
+    let mut position = 0_u64;
+
+    for &(offset, size) in map.iter() {
+        let hole = offset
+            .checked_sub(position)
+            .context("Regions of sparse map are not in order")?;
+
+        writer.write_hole(hole)?;
+
+        let copied = io::copy(&mut (&mut buf_reader).take(size), writer)?;
+
+        anyhow::ensure!(
+            copied == size,
+            "Data of sparse file ends before the regions of its sparse map"
+        );
+
+        position = offset
+            .checked_add(size)
+            .context("Region of sparse map is too large")?;
+    }
+
+    let hole = pax_sparse_file
+        .real_size
+        .checked_sub(position)
+        .context("Sparse map extends beyond the size of the file")?;
+
+    writer.write_hole(hole)?;
+
+    Ok(())
+}
+
+/// Reads the sparse map of version 1.0: the number of regions, then the offset and size of each region, each on its
+/// own line
+fn read_sparse_map(reader: &mut impl BufRead) -> anyhow::Result<Vec<(u64, u64)>> {
+    let mut read = 0_u64;
+
+    let mut read_number = || -> anyhow::Result<u64> {
+        let mut line = Vec::new();
+
+        (&mut *reader)
+            .take(MAX_MAP_LINE_LENGTH)
+            .read_until(b'\n', &mut line)?;
+
+        read = read.saturating_add(u64::try_from(line.len())?);
+
+        let digits = line
+            .strip_suffix(b"\n")
+            .context("Sparse map stored before the data is truncated")?;
+
+        let number = String::from_utf8_lossy(digits)
+            .parse::<u64>()
+            .with_context(|| {
+                format!(
+                    "Sparse map stored before the data contains \"{}\", which is not a number",
+                    String::from_utf8_lossy(digits)
+                )
+            })?;
+
+        Ok(number)
+    };
+
+    let count = read_number()?;
+
+    // Not preallocated, since `count` is not trusted
+    let mut map = Vec::new();
+
+    for _ in 0_u64..count {
+        map.push((read_number()?, read_number()?));
+    }
+
+    let padding = TAR_BLOCK_SIZE
+        .wrapping_sub(read.wrapping_rem(TAR_BLOCK_SIZE))
+        .wrapping_rem(TAR_BLOCK_SIZE);
+
+    io::copy(&mut reader.take(padding), &mut io::sink())?;
+
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PaxSparseFile, SparseWriter, TAR_BLOCK_SIZE};
+    use std::{
+        env, fs,
+        io::{BufWriter, Write},
+        process,
+    };
+    use tar::{Archive, EntryType, Header};
+
+    /// Expanded size of the sparse file used by the tests
+    const REAL_SIZE: u64 = 10_000;
+
+    /// Data regions of the sparse file used by the tests, with a hole between them and at the end
+    const REGIONS: [(u64, &[u8]); 2] = [(0, b"hello"), (8_192, b"world")];
+
+    fn get_expanded_file() -> Vec<u8> {
+        let mut expanded = vec![0_u8; usize::try_from(REAL_SIZE).unwrap_or_default()];
+
+        for (offset, data) in REGIONS {
+            let start = usize::try_from(offset).unwrap_or_default();
+
+            if let Some(sl) = expanded.get_mut(start..start.saturating_add(data.len())) {
+                sl.copy_from_slice(data);
+            }
+        }
+
+        expanded
+    }
+
+    /// Encodes PAX records. The length of each record includes the digits of the length itself.
+    fn encode_pax_records(records: &[(&str, &str)]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+
+        for &(key, value) in records {
+            // " ", "=", and "\n"
+            let length_without_digits = key.len().saturating_add(value.len()).saturating_add(3);
+
+            let mut length = length_without_digits;
+
+            while length_without_digits.saturating_add(length.to_string().len()) != length {
+                length = length_without_digits.saturating_add(length.to_string().len());
+            }
+
+            encoded.extend_from_slice(format!("{length} {key}={value}\n").as_bytes());
+        }
+
+        encoded
+    }
+
+    /// Builds a tar archive with a single sparse file in one of the PAX formats, and expands it
+    fn expand(
+        path: &str,
+        records: &[(&str, &str)],
+        data: &[u8],
+    ) -> anyhow::Result<(String, Vec<u8>)> {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let pax_records = encode_pax_records(records);
+
+        let mut pax_header = Header::new_ustar();
+
+        pax_header.set_path("PaxHeaders/file")?;
+        pax_header.set_entry_type(EntryType::XHeader);
+        pax_header.set_size(u64::try_from(pax_records.len())?);
+        pax_header.set_cksum();
+
+        builder.append(&pax_header, pax_records.as_slice())?;
+
+        let mut header = Header::new_ustar();
+
+        header.set_path(path)?;
+        header.set_entry_type(EntryType::Regular);
+        header.set_mode(0o644);
+        header.set_size(u64::try_from(data.len())?);
+        header.set_cksum();
+
+        builder.append(&header, data)?;
+
+        let archive_bytes = builder.into_inner()?;
+
+        let mut archive = Archive::new(archive_bytes.as_slice());
+
+        let mut entry = archive
+            .entries()?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Archive has no entries"))??;
+
+        let pax_sparse_file = PaxSparseFile::from_entry(&mut entry)?
+            .ok_or_else(|| anyhow::anyhow!("Entry is not a sparse file"))?;
+
+        anyhow::ensure!(
+            pax_sparse_file.get_real_size() == REAL_SIZE,
+            "Real size was not read"
+        );
+
+        let entry_path = super::get_tar_entry_path(&entry, Some(&pax_sparse_file))?;
+
+        let mut buf_writer = BufWriter::new(Vec::new());
+
+        super::copy_pax_sparse_file(&mut entry, &mut buf_writer, &pax_sparse_file)?;
+
+        Ok((
+            entry_path.to_string_lossy().into_owned(),
+            buf_writer.into_inner()?,
+        ))
+    }
+
+    #[test]
+    fn pax_version_zero_zero() -> anyhow::Result<()> {
+        let (entry_path, expanded) = expand(
+            "file",
+            &[
+                ("GNU.sparse.size", "10000"),
+                ("GNU.sparse.numblocks", "2"),
+                ("GNU.sparse.offset", "0"),
+                ("GNU.sparse.numbytes", "5"),
+                ("GNU.sparse.offset", "8192"),
+                ("GNU.sparse.numbytes", "5"),
+            ],
+            b"helloworld",
+        )?;
+
+        anyhow::ensure!(entry_path == "file", "Path is \"{entry_path}\"");
+        anyhow::ensure!(expanded == get_expanded_file(), "File was not expanded");
+
+        Ok(())
+    }
+
+    #[test]
+    fn pax_version_zero_one() -> anyhow::Result<()> {
+        let (entry_path, expanded) = expand(
+            "GNUSparseFile.1234/file",
+            &[
+                ("GNU.sparse.size", "10000"),
+                ("GNU.sparse.numblocks", "2"),
+                ("GNU.sparse.name", "file"),
+                ("GNU.sparse.map", "0,5,8192,5"),
+            ],
+            b"helloworld",
+        )?;
+
+        anyhow::ensure!(entry_path == "file", "Path is \"{entry_path}\"");
+        anyhow::ensure!(expanded == get_expanded_file(), "File was not expanded");
+
+        Ok(())
+    }
+
+    #[test]
+    fn pax_version_one_zero() -> anyhow::Result<()> {
+        // The map is padded to a multiple of the tar block size
+        let mut data = b"2\n0\n5\n8192\n5\n".to_vec();
+
+        data.resize(usize::try_from(TAR_BLOCK_SIZE)?, 0_u8);
+        data.extend_from_slice(b"helloworld");
+
+        let (entry_path, expanded) = expand(
+            "GNUSparseFile.1234/file",
+            &[
+                ("GNU.sparse.major", "1"),
+                ("GNU.sparse.minor", "0"),
+                ("GNU.sparse.name", "file"),
+                ("GNU.sparse.realsize", "10000"),
+            ],
+            &data,
+        )?;
+
+        anyhow::ensure!(entry_path == "file", "Path is \"{entry_path}\"");
+        anyhow::ensure!(expanded == get_expanded_file(), "File was not expanded");
+
+        Ok(())
+    }
+
+    #[test]
+    fn pax_truncated_data() {
+        let result = expand(
+            "GNUSparseFile.1234/file",
+            &[
+                ("GNU.sparse.size", "10000"),
+                ("GNU.sparse.name", "file"),
+                ("GNU.sparse.map", "0,5,8192,5"),
+            ],
+            b"hello",
+        );
+
+        assert!(result.is_err(), "Truncated data was expanded");
+    }
+
+    /// Writes `writes` with a `SparseWriter`, and returns what was written to the file
+    fn write_sparse(name: &str, writes: &[&[u8]]) -> anyhow::Result<Vec<u8>> {
+        let path = env::temp_dir().join(format!("tarx-sparse-{name}-{}", process::id()));
+
+        let result = (|| {
+            let mut sparse_writer = SparseWriter::new(fs::File::create_new(path.as_path())?);
+
+            for sl in writes {
+                sparse_writer.write_all(sl)?;
+            }
+
+            sparse_writer.finish()?;
+
+            anyhow::Ok(fs::read(path.as_path())?)
+        })();
+
+        fs::remove_file(path.as_path()).ok();
+
+        result
+    }
+
+    #[test]
+    fn sparse_writer_data_hole_data() -> anyhow::Result<()> {
+        let mut expected = b"data".to_vec();
+
+        expected.resize(20_000, 0_u8);
+        expected.extend_from_slice(b"data");
+
+        // Writes that are not aligned to chunks, and a hole that is split across writes
+        let written = write_sparse(
+            "data-hole-data",
+            &[b"data", &[0_u8; 5_000], &[0_u8; 14_996], b"data"],
+        )?;
+
+        anyhow::ensure!(
+            written.len() == expected.len(),
+            "Length is {}",
+            written.len()
+        );
+        anyhow::ensure!(written == expected, "Contents are not what was written");
+
+        Ok(())
+    }
+
+    #[test]
+    fn sparse_writer_trailing_hole() -> anyhow::Result<()> {
+        let mut expected = b"data".to_vec();
+
+        expected.resize(12_345, 0_u8);
+
+        let written = write_sparse("trailing-hole", &[expected.as_slice()])?;
+
+        anyhow::ensure!(
+            written.len() == expected.len(),
+            "Length is {}",
+            written.len()
+        );
+        anyhow::ensure!(written == expected, "Contents are not what was written");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use std::path::Path;
+
+/// Setuid, setgid, and sticky bits of a Unix mode. Removed unless "--keep-special-bits" is passed.
+pub const SPECIAL_MODE_BITS: u32 = 0o7000;
+
+/// File type bits of a Unix mode, as stored by zip and 7z archives
+const FILE_TYPE_BITS: u32 = 0o170_000;
+const FILE_TYPE_FIFO: u32 = 0o010_000;
+const FILE_TYPE_CHARACTER_DEVICE: u32 = 0o020_000;
+const FILE_TYPE_BLOCK_DEVICE: u32 = 0o060_000;
+const FILE_TYPE_SYMLINK: u32 = 0o120_000;
+
+/// Whether the file type bits of a Unix mode are those of a symlink
+pub fn is_symlink_mode(mode: u32) -> bool {
+    mode & FILE_TYPE_BITS == FILE_TYPE_SYMLINK
+}
+
+/// Kinds of entries that are skipped unless "--special-files" is passed
+#[derive(Clone, Copy)]
+pub enum SpecialFileKind {
+    BlockDevice,
+    CharacterDevice,
+    Fifo,
+}
+
+impl SpecialFileKind {
+    /// Gets the kind from the file type bits of a Unix mode
+    pub fn from_mode(mode: u32) -> Option<SpecialFileKind> {
+        match mode & FILE_TYPE_BITS {
+            FILE_TYPE_BLOCK_DEVICE => Some(SpecialFileKind::BlockDevice),
+            FILE_TYPE_CHARACTER_DEVICE => Some(SpecialFileKind::CharacterDevice),
+            FILE_TYPE_FIFO => Some(SpecialFileKind::Fifo),
+            _ => None,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            SpecialFileKind::BlockDevice => "block device",
+            SpecialFileKind::CharacterDevice => "character device",
+            SpecialFileKind::Fifo => "FIFO",
+        }
+    }
+
+    /// Device nodes cannot be created without a device number
+    pub fn is_device(self) -> bool {
+        match self {
+            SpecialFileKind::BlockDevice | SpecialFileKind::CharacterDevice => true,
+            SpecialFileKind::Fifo => false,
+        }
+    }
+}
+
+/// A device node or FIFO entry
+pub struct SpecialFile {
+    /// Major and minor device numbers. Only tar archives store these.
+    pub device: Option<(u32, u32)>,
+    pub kind: SpecialFileKind,
+}
+
+/// Creates a device node or FIFO with the permission bits of `mode` (see `Extractor::get_mode`), or 644 if the archive
+/// does not store a mode. Not implemented for non-Unix platforms.
+pub fn create_special_file(
+    path: &Path,
+    special_file: &SpecialFile,
+    mode: Option<u32>,
+) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::{
+            ffi::CString,
+            fs::{self, Permissions},
+            io,
+            os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+        };
+
+        let file_type = match special_file.kind {
+            SpecialFileKind::BlockDevice => libc::S_IFBLK,
+            SpecialFileKind::CharacterDevice => libc::S_IFCHR,
+            SpecialFileKind::Fifo => libc::S_IFIFO,
+        };
+
+        let permission_bits = mode.unwrap_or(0o644_u32);
+
+        let (major, minor) = special_file.device.unwrap_or_default();
+
+        #[allow(
+            clippy::useless_conversion,
+            clippy::unnecessary_fallible_conversions,
+            reason = "The types of these parameters vary by platform"
+        )]
+        let (mode_t, device) = (
+            file_type | libc::mode_t::try_from(permission_bits)?,
+            libc::makedev(major.try_into()?, minor.try_into()?),
+        );
+
+        let path_c_string = CString::new(path.as_os_str().as_bytes())?;
+
+        // Safety: `path_c_string` is a valid C string
+        let result = unsafe { libc::mknod(path_c_string.as_ptr(), mode_t, device) };
+
+        if result != 0_i32 {
+            let error = io::Error::last_os_error();
+
+            anyhow::bail!(
+                "Could not create {} \"{}\": {error}",
+                special_file.kind.description(),
+                path.display()
+            );
+        }
+
+        // `mknod` applies the umask
+        fs::set_permissions(path, Permissions::from_mode(permission_bits))?;
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        anyhow::bail!(
+            "Creating a {} (\"{}\") is not implemented for this platform",
+            special_file.kind.description(),
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use std::{io, path::Path};
+
+/// Creates a symlink at `path` pointing to `target`
+#[cfg(unix)]
+pub fn create_symlink(path: &Path, target: &Path) -> io::Result<()> {
+    use std::os::unix::fs as unix_fs;
+
+    unix_fs::symlink(target, path)
+}
+
+/// Creates a symlink at `path` pointing to `target`. Zip archives do not store whether the target is a directory, so
+/// a file symlink is created.
+#[cfg(windows)]
+pub fn create_symlink(path: &Path, target: &Path) -> io::Result<()> {
+    use std::os::windows::fs as windows_fs;
+
+    windows_fs::symlink_file(target, path)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn create_symlink(_: &Path, _: &Path) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Whether creating a symlink failed because symlinks are disabled: the platform does not support them, or the user is
+/// not allowed to create them (on Windows without Developer Mode). Other errors, including permission errors on the
+/// destination directory, are not.
+pub fn is_disabled_error(error: &io::Error) -> bool {
+    /// `ERROR_PRIVILEGE_NOT_HELD`
+    #[cfg(windows)]
+    const PRIVILEGE_NOT_HELD: i32 = 1314;
+
+    #[cfg(windows)]
+    if error.raw_os_error() == Some(PRIVILEGE_NOT_HELD) {
+        return true;
+    }
+
+    error.kind() == io::ErrorKind::Unsupported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_disabled_error;
+    use std::io::{Error, ErrorKind};
+
+    #[test]
+    fn disabled_errors() {
+        assert!(is_disabled_error(&Error::from(ErrorKind::Unsupported)));
+
+        // e.g. EACCES when the destination directory is not writable
+        assert!(!is_disabled_error(&Error::from(
+            ErrorKind::PermissionDenied
+        )));
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::Context;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeSet,
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Flushes extracted entries to disk (with "--sync"), so that they survive a crash or power loss once tarx has exited,
+/// and keeps track of how long that took
+pub struct SyncTracker {
+    /// Directories containing extracted entries (and the new directory itself), which are synced once every entry has
+    /// been extracted
+    directories: RefCell<BTreeSet<PathBuf>>,
+    new_directory: PathBuf,
+    /// Directories that could not be opened (e.g. extracted without read permission)
+    skipped_directories: Cell<u64>,
+    synced_directories: Cell<u64>,
+    synced_files: Cell<u64>,
+    time: Cell<Duration>,
+}
+
+impl SyncTracker {
+    pub fn new(new_directory: &Path) -> SyncTracker {
+        SyncTracker {
+            directories: RefCell::new(BTreeSet::new()),
+            new_directory: new_directory.to_path_buf(),
+            skipped_directories: Cell::new(0_u64),
+            synced_directories: Cell::new(0_u64),
+            synced_files: Cell::new(0_u64),
+            time: Cell::new(Duration::ZERO),
+        }
+    }
+
+    /// Called once an entry has been written to `path` and its metadata has been set. Regular files are synced
+    /// immediately. The directories containing the entry are recorded, and synced by `sync_directories`.
+    pub fn sync_entry(&self, path: &Path, is_file: bool, is_directory: bool) -> anyhow::Result<()> {
+        {
+            let mut directories = self.directories.borrow_mut();
+
+            // Parent directories created for the entry (see `Extractor::create_parent_directory`) have no entries of
+            // their own
+            for pa in path
+                .ancestors()
+                .skip(usize::from(!is_directory))
+                .take_while(|pa| pa.starts_with(self.new_directory.as_path()))
+            {
+                if !directories.insert(pa.to_path_buf()) {
+                    break;
+                }
+            }
+        }
+
+        if is_file {
+            self.time_sync(|| sync_file(path))
+                .with_context(|| format!("Could not sync \"{}\" to disk", path.display()))?;
+
+            self.synced_files
+                .set(self.synced_files.get().saturating_add(1_u64));
+        }
+
+        Ok(())
+    }
+
+    /// Called once every entry has been extracted, and the times of directories have been set. Deeper directories are
+    /// synced first.
+    pub fn sync_directories(&self) -> anyhow::Result<()> {
+        for pa in self.directories.borrow().iter().rev() {
+            self.sync_directory(pa.as_path())?;
+        }
+
+        Ok(())
+    }
+
+    /// Called once the staging directory has been renamed to `new_directory`. The rename is only durable once the
+    /// directory containing `new_directory` has been synced.
+    pub fn sync_rename(&self, new_directory: &Path) -> anyhow::Result<()> {
+        let parent = match new_directory.parent() {
+            Some(pa) if !pa.as_os_str().is_empty() => pa,
+            _ => Path::new("."),
+        };
+
+        self.sync_directory(parent)
+    }
+
+    /// Called at the end of the run
+    pub fn report(&self) {
+        let skipped_directories = self.skipped_directories.get();
+
+        if skipped_directories != 0_u64 {
+            tracing::warn!(
+                skipped_directories,
+                "Could not sync some directories to disk, since they could not be opened"
+            );
+        }
+
+        tracing::info!(
+            synced_files = self.synced_files.get(),
+            synced_directories = self.synced_directories.get(),
+            sync_time = ?self.time.get(),
+            "Synced extracted entries to disk"
+        );
+    }
+
+    /// Directories cannot be opened as files on other platforms, and do not need to be synced on Windows. Directories
+    /// that were extracted without read permission cannot be opened by other users than root, and are skipped.
+    fn sync_directory(&self, path: &Path) -> anyhow::Result<()> {
+        if cfg!(unix) {
+            match self.time_sync(|| File::open(path).and_then(|fi| fi.sync_all())) {
+                Ok(()) => {
+                    self.synced_directories
+                        .set(self.synced_directories.get().saturating_add(1_u64));
+                }
+                Err(er) if er.kind() == ErrorKind::PermissionDenied => {
+                    tracing::debug!(
+                        path = %path.display(),
+                        "Could not open directory to sync it to disk"
+                    );
+
+                    self.skipped_directories
+                        .set(self.skipped_directories.get().saturating_add(1_u64));
+                }
+                Err(er) => {
+                    return Err(er)
+                        .with_context(|| format!("Could not sync \"{}\" to disk", path.display()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn time_sync(&self, sync: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+        let start = Instant::now();
+
+        let result = sync();
+
+        self.time
+            .set(self.time.get().saturating_add(start.elapsed()));
+
+        result
+    }
+}
+
+/// Files are opened for reading, or for writing if they were extracted without read permission. Files without either
+/// permission are made readable while they are synced.
+fn sync_file(path: &Path) -> io::Result<()> {
+    for open_options in [
+        OpenOptions::new().read(true),
+        OpenOptions::new().write(true),
+    ] {
+        match open_options.open(path) {
+            Ok(fi) => return fi.sync_all(),
+            Err(er) if er.kind() == ErrorKind::PermissionDenied => {}
+            Err(er) => return Err(er),
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        use std::{
+            fs::{self, Permissions},
+            os::unix::fs::PermissionsExt,
+        };
+
+        let permissions = fs::symlink_metadata(path)?.permissions();
+
+        fs::set_permissions(path, Permissions::from_mode(permissions.mode() | 0o400))?;
+
+        let result = File::open(path).and_then(|fi| fi.sync_all());
+
+        fs::set_permissions(path, permissions)?;
+
+        result
+    }
+
+    #[cfg(not(unix))]
+    {
+        File::open(path).and_then(|fi| fi.sync_all())
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Context;
+use filetime_creation::FileTime;
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// Seconds from 1601-01-01 (the epoch of Windows file times) to 1970-01-01
+const WINDOWS_EPOCH_OFFSET_SECONDS: u64 = 11_644_473_600;
+/// Windows file times count 100-nanosecond intervals
+const WINDOWS_INTERVALS_PER_SECOND: u64 = 10_000_000;
+
+/// The times of an entry, as stored in the archive
+#[derive(Clone, Copy)]
+pub struct EntryTimes {
+    pub access: Option<SystemTime>,
+    /// Only set on Windows
+    pub creation: Option<SystemTime>,
+    pub modification: Option<SystemTime>,
+}
+
+impl EntryTimes {
+    /// Sets the times of `path` (without following symlinks). Nothing is changed if the archive does not store a
+    /// modification time. The access and creation times default to the modification time, like they do for tar.
+    pub fn apply(self, path: &Path) -> anyhow::Result<()> {
+        let Some(sy) = self.modification else {
+            return Ok(());
+        };
+
+        let modification_file_time = FileTime::from_system_time(sy);
+
+        let get_file_time = |option: Option<SystemTime>| {
+            option.map_or(modification_file_time, FileTime::from_system_time)
+        };
+
+        filetime_creation::set_symlink_file_times(
+            path,
+            get_file_time(self.access),
+            modification_file_time,
+            get_file_time(self.creation),
+        )
+        .with_context(|| format!("Could not set the times of \"{}\"", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Converts a Windows file time (as stored by the NTFS extra field of zip archives). Returns `None` for times before
+/// 1970, which are treated as not stored.
+pub fn from_windows_file_time(file_time: u64) -> Option<SystemTime> {
+    let seconds = file_time
+        .checked_div(WINDOWS_INTERVALS_PER_SECOND)?
+        .checked_sub(WINDOWS_EPOCH_OFFSET_SECONDS)?;
+
+    let nanoseconds = file_time
+        .checked_rem(WINDOWS_INTERVALS_PER_SECOND)?
+        .checked_mul(100_u64)?;
+
+    SystemTime::UNIX_EPOCH.checked_add(Duration::new(seconds, u32::try_from(nanoseconds).ok()?))
+}
+
+/// Converts a date and time in the local time zone of this system (e.g. the DOS time stored by zip archives, which is
+/// the local time of the system that created the archive). Returns `None` if the time does not exist.
+#[cfg(unix)]
+pub fn from_local_time(
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+) -> Option<SystemTime> {
+    use std::mem;
+
+    // Safety: `tm` is a plain C struct, for which all zeros is valid
+    let mut tm = unsafe { mem::zeroed::<libc::tm>() };
+
+    tm.tm_year = i32::from(year).checked_sub(1_900_i32)?;
+    tm.tm_mon = i32::from(month).checked_sub(1_i32)?;
+    tm.tm_mday = i32::from(day);
+    tm.tm_hour = i32::from(hour);
+    tm.tm_min = i32::from(minute);
+    tm.tm_sec = i32::from(second);
+    // Whether daylight saving time applies is determined by `mktime`
+    tm.tm_isdst = -1_i32;
+
+    // Safety: `tm` is a valid struct, which `mktime` only normalizes
+    let seconds = unsafe { libc::mktime(&raw mut tm) };
+
+    // DOS times cannot be before 1980, so -1 is always an error
+    SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(u64::try_from(seconds).ok()?))
+}
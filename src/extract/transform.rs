@@ -0,0 +1,143 @@
+use anyhow::Context;
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+
+/// A sed-style substitution rule (e.g. "s,^pkg-1.2/,pkg/,") that is applied to the path of each entry
+#[derive(Clone)]
+pub struct Transform {
+    global: bool,
+    regex: Regex,
+    /// In the syntax of `Regex::replace`
+    replacement: String,
+}
+
+impl Transform {
+    /// Parses "s/REGEX/REPLACEMENT/FLAGS". Any character can be used instead of "/", and can be escaped with "\" in
+    /// `REGEX` and `REPLACEMENT`. In `REPLACEMENT`, "&" is replaced with the whole match, and "\1" to "\9" with capture
+    /// groups. The supported flags are "g" (replace all matches, not just the first) and "i" (case-insensitive).
+    pub fn parse(expression: &str) -> anyhow::Result<Transform> {
+        let rest = expression.strip_prefix('s').with_context(|| {
+            format!("\"{expression}\" is not a valid transform expression (it should look like \"s/REGEX/REPLACEMENT/\")")
+        })?;
+
+        let mut chars = rest.chars();
+
+        let delimiter = chars
+            .next()
+            .filter(|ch| !ch.is_alphanumeric() && *ch != '\\')
+            .with_context(|| format!("\"{expression}\" does not have a valid delimiter"))?;
+
+        let mut parts = Vec::<String>::with_capacity(3_usize);
+
+        let mut part = String::new();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => match chars.next() {
+                    // The delimiter is matched literally, even if it has a meaning in regular expressions
+                    Some(cha) if cha == delimiter && parts.is_empty() => {
+                        part.push_str(
+                            regex::escape(cha.encode_utf8(&mut [0_u8; 4_usize])).as_str(),
+                        );
+                    }
+                    Some(cha) if cha == delimiter => {
+                        part.push(cha);
+                    }
+                    Some(cha) => {
+                        part.push('\\');
+                        part.push(cha);
+                    }
+                    None => {
+                        anyhow::bail!("\"{expression}\" ends with an unfinished escape sequence");
+                    }
+                },
+                cha if cha == delimiter && parts.len() < 2_usize => {
+                    parts.push(part);
+
+                    part = String::new();
+                }
+                cha => {
+                    part.push(cha);
+                }
+            }
+        }
+
+        let (Some(pattern), Some(replacement)) = (parts.first(), parts.get(1_usize)) else {
+            anyhow::bail!(
+                "\"{expression}\" is not a valid transform expression (it should look like \"s/REGEX/REPLACEMENT/\")"
+            );
+        };
+
+        // `part` now contains the flags
+        let mut global = false;
+        let mut case_insensitive = false;
+
+        for ch in part.chars() {
+            match ch {
+                'g' => global = true,
+                'i' => case_insensitive = true,
+                _ => {
+                    anyhow::bail!(
+                        "Unrecognized flag \"{ch}\" in \"{expression}\". Supported flags are \"g\" and \"i\"."
+                    );
+                }
+            }
+        }
+
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .build()?;
+
+        Ok(Transform {
+            global,
+            regex,
+            replacement: convert_replacement(replacement)?,
+        })
+    }
+
+    pub fn apply<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        if self.global {
+            self.regex.replace_all(path, self.replacement.as_str())
+        } else {
+            self.regex.replace(path, self.replacement.as_str())
+        }
+    }
+}
+
+/// Converts a sed replacement ("&", "\1") into the syntax used by `Regex::replace` ("${0}", "${1}")
+fn convert_replacement(replacement: &str) -> anyhow::Result<String> {
+    let mut converted = String::with_capacity(replacement.len());
+
+    let mut chars = replacement.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '$' => {
+                converted.push_str("$$");
+            }
+            '&' => {
+                converted.push_str("${0}");
+            }
+            '\\' => match chars.next() {
+                Some(cha) if cha.is_ascii_digit() => {
+                    converted.push_str("${");
+                    converted.push(cha);
+                    converted.push('}');
+                }
+                Some(cha) => {
+                    converted.push(cha);
+                }
+                None => {
+                    anyhow::bail!(
+                        "Replacement \"{replacement}\" ends with an unfinished escape sequence"
+                    );
+                }
+            },
+            cha => {
+                converted.push(cha);
+            }
+        }
+    }
+
+    Ok(converted)
+}
@@ -0,0 +1,508 @@
+use crate::extract::ownership;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str,
+};
+
+/// PAX record prefix used by GNU tar and star. The value is stored as is.
+const SCHILY_XATTR_PREFIX: &str = "SCHILY.xattr.";
+/// PAX record prefix used by libarchive (bsdtar). The name is percent-encoded, and the value is base64-encoded.
+const LIBARCHIVE_XATTR_PREFIX: &str = "LIBARCHIVE.xattr.";
+/// PAX record used by the tar of Red Hat distributions for SELinux labels
+const RHT_SELINUX_KEY: &str = "RHT.security.selinux";
+/// PAX records used by GNU tar and star for POSIX ACLs, in the short text form (entries separated by commas)
+const SCHILY_ACL_ACCESS_KEY: &str = "SCHILY.acl.access";
+const SCHILY_ACL_DEFAULT_KEY: &str = "SCHILY.acl.default";
+
+const ACL_ACCESS_NAME: &str = "system.posix_acl_access";
+const ACL_DEFAULT_NAME: &str = "system.posix_acl_default";
+const SELINUX_NAME: &str = "security.selinux";
+
+/// Which extended attributes are restored (and shown in list mode). Nothing is restored by default.
+#[derive(Default)]
+pub struct XattrOptions {
+    /// POSIX ACLs ("--acls")
+    pub acls: bool,
+    /// SELinux labels ("--selinux")
+    pub selinux: bool,
+    /// Every other extended attribute ("--xattrs")
+    pub xattrs: bool,
+}
+
+impl XattrOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.acls || self.selinux || self.xattrs
+    }
+
+    pub fn is_selected(&self, xattr: &Xattr) -> bool {
+        match xattr.get_kind() {
+            XattrKind::Acl => self.acls,
+            XattrKind::Other => self.xattrs,
+            XattrKind::Selinux => self.selinux,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+enum XattrKind {
+    Acl,
+    Other,
+    Selinux,
+}
+
+impl XattrKind {
+    fn description(self) -> &'static str {
+        match self {
+            XattrKind::Acl => "ACLs",
+            XattrKind::Other => "extended attributes",
+            XattrKind::Selinux => "SELinux labels",
+        }
+    }
+}
+
+/// An extended attribute stored in the archive, with the value in the form the file system expects
+pub struct Xattr {
+    /// The text form of ACLs, which is shown instead of the binary value
+    acl_text: Option<String>,
+    name: String,
+    value: Vec<u8>,
+}
+
+impl Xattr {
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn get_kind(&self) -> XattrKind {
+        match self.name.as_str() {
+            ACL_ACCESS_NAME | ACL_DEFAULT_NAME => XattrKind::Acl,
+            SELINUX_NAME => XattrKind::Selinux,
+            _ => XattrKind::Other,
+        }
+    }
+
+    /// The value as a string if it is printable, otherwise as hex (like "getfattr")
+    pub fn get_display_value(&self) -> String {
+        if let Some(ref st) = self.acl_text {
+            return format!("\"{st}\"");
+        }
+
+        match str::from_utf8(self.value.as_slice()) {
+            Ok(st) if !st.chars().any(char::is_control) => format!("\"{st}\""),
+            _ => format!("0x{}", hex::encode(self.value.as_slice())),
+        }
+    }
+}
+
+/// Converts a PAX record into an extended attribute. Returns `None` for records that are not extended attributes.
+pub fn parse_pax_record(key: &str, value: &[u8]) -> anyhow::Result<Option<Xattr>> {
+    let xattr = if let Some(st) = key.strip_prefix(SCHILY_XATTR_PREFIX) {
+        Xattr {
+            acl_text: None,
+            name: st.to_owned(),
+            value: value.to_vec(),
+        }
+    } else if let Some(st) = key.strip_prefix(LIBARCHIVE_XATTR_PREFIX) {
+        Xattr {
+            acl_text: None,
+            name: decode_percent(st)?,
+            value: decode_base64(value)?,
+        }
+    } else if key == RHT_SELINUX_KEY {
+        Xattr {
+            acl_text: None,
+            name: SELINUX_NAME.to_owned(),
+            value: value.to_vec(),
+        }
+    } else if key == SCHILY_ACL_ACCESS_KEY || key == SCHILY_ACL_DEFAULT_KEY {
+        let acl_text = str::from_utf8(value)?;
+
+        Xattr {
+            acl_text: Some(acl_text.to_owned()),
+            name: if key == SCHILY_ACL_ACCESS_KEY {
+                ACL_ACCESS_NAME
+            } else {
+                ACL_DEFAULT_NAME
+            }
+            .to_owned(),
+            value: encode_acl(acl_text)?,
+        }
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(xattr))
+}
+
+/// Collects the extended attributes that could not be set, so that they can be reported once at the end instead of for
+/// every entry (e.g. when the file system does not support them)
+#[derive(Default)]
+pub struct XattrTracker {
+    failures: RefCell<BTreeMap<XattrKind, (u64, PathBuf, String)>>,
+    restored: Cell<u64>,
+}
+
+impl XattrTracker {
+    /// Sets the selected extended attributes on `path` (without following symlinks). Failures are recorded (with
+    /// `entry_path`) instead of returned.
+    pub fn apply(
+        &self,
+        xattr_options: &XattrOptions,
+        path: &Path,
+        entry_path: &Path,
+        xattrs: &[Xattr],
+    ) {
+        for xa in xattrs {
+            if !xattr_options.is_selected(xa) {
+                continue;
+            }
+
+            match set_xattr(path, xa) {
+                Ok(()) => {
+                    self.restored.set(self.restored.get().saturating_add(1_u64));
+                }
+                Err(er) => {
+                    let mut failures = self.failures.borrow_mut();
+
+                    let failure = failures
+                        .entry(xa.get_kind())
+                        .or_insert_with(|| (0_u64, entry_path.to_path_buf(), er.to_string()));
+
+                    failure.0 = failure.0.saturating_add(1_u64);
+                }
+            }
+        }
+    }
+
+    /// Called once every entry has been extracted
+    pub fn report(&self) {
+        let restored = self.restored.get();
+
+        if restored != 0_u64 {
+            tracing::info!(restored, "Restored extended attributes");
+        }
+
+        for (xa, &(failed, ref pa, ref st)) in self.failures.borrow().iter() {
+            tracing::warn!(
+                failed,
+                first_entry_path = %pa.display(),
+                first_error = st,
+                "Could not restore {}",
+                xa.description()
+            );
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_xattr(path: &Path, xattr: &Xattr) -> anyhow::Result<()> {
+    use std::{ffi::CString, io, os::unix::ffi::OsStrExt};
+
+    let path_c_string = CString::new(path.as_os_str().as_bytes())?;
+
+    let name_c_string = CString::new(xattr.name.as_str())?;
+
+    // Safety: `path_c_string` and `name_c_string` are valid C strings, and the value pointer and length come from the
+    // same slice
+    let result = unsafe {
+        libc::lsetxattr(
+            path_c_string.as_ptr(),
+            name_c_string.as_ptr(),
+            xattr.value.as_ptr().cast(),
+            xattr.value.len(),
+            0_i32,
+        )
+    };
+
+    if result != 0_i32 {
+        anyhow::bail!("{}: {}", xattr.name, io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_xattr(_: &Path, xattr: &Xattr) -> anyhow::Result<()> {
+    anyhow::bail!(
+        "{}: setting extended attributes is not implemented for this platform",
+        xattr.name
+    );
+}
+
+/// Converts the text form of a POSIX ACL into the binary form stored in the ACL extended attributes. Entries
+/// are separated by "," or newlines, and have the form "TAG:QUALIFIER:PERMISSIONS", optionally followed by ":ID" (which
+/// star adds for named users and groups).
+fn encode_acl(text: &str) -> anyhow::Result<Vec<u8>> {
+    const ACL_EA_VERSION: u32 = 2;
+    const ACL_UNDEFINED_ID: u32 = u32::MAX;
+    const ACL_USER_OBJ: u16 = 0x01;
+    const ACL_USER: u16 = 0x02;
+    const ACL_GROUP_OBJ: u16 = 0x04;
+    const ACL_GROUP: u16 = 0x08;
+    const ACL_MASK: u16 = 0x10;
+    const ACL_OTHER: u16 = 0x20;
+
+    let mut entries = Vec::<(u16, u32, u16)>::new();
+
+    for st in text.split([',', '\n']) {
+        let acl_entry = st.trim();
+
+        if acl_entry.is_empty() {
+            continue;
+        }
+
+        let mut split = acl_entry.split(':');
+
+        let (Some(tag), Some(qualifier), Some(permissions)) =
+            (split.next(), split.next(), split.next())
+        else {
+            anyhow::bail!("\"{acl_entry}\" is not a valid ACL entry");
+        };
+
+        let numeric_id = split.next();
+
+        let get_id = |parse: fn(&str) -> anyhow::Result<u32>| {
+            numeric_id
+                .and_then(|str| str.parse::<u32>().ok())
+                .map_or_else(|| parse(qualifier), Ok)
+        };
+
+        let (tag_value, id) = match (tag, qualifier.is_empty()) {
+            ("user" | "u", true) => (ACL_USER_OBJ, ACL_UNDEFINED_ID),
+            ("user" | "u", false) => (ACL_USER, get_id(ownership::parse_user)?),
+            ("group" | "g", true) => (ACL_GROUP_OBJ, ACL_UNDEFINED_ID),
+            ("group" | "g", false) => (ACL_GROUP, get_id(ownership::parse_group)?),
+            ("mask" | "m", _) => (ACL_MASK, ACL_UNDEFINED_ID),
+            ("other" | "o", _) => (ACL_OTHER, ACL_UNDEFINED_ID),
+            _ => {
+                anyhow::bail!("\"{acl_entry}\" has an unrecognized ACL tag");
+            }
+        };
+
+        let mut permission_bits = 0_u16;
+
+        let mut chars = permissions.chars();
+
+        // Each position is either its permission or "-"
+        for (letter, bit) in [('r', 4_u16), ('w', 2_u16), ('x', 1_u16)] {
+            match chars.next() {
+                Some(ch) if ch == letter => {
+                    permission_bits |= bit;
+                }
+                Some('-') => {}
+                _ => {
+                    anyhow::bail!("\"{acl_entry}\" has invalid ACL permissions");
+                }
+            }
+        }
+
+        anyhow::ensure!(
+            chars.next().is_none(),
+            "\"{acl_entry}\" has invalid ACL permissions"
+        );
+
+        entries.push((tag_value, id, permission_bits));
+    }
+
+    // The kernel requires entries to be sorted by tag, and then by ID
+    entries.sort_unstable();
+
+    let mut bytes = Vec::with_capacity(
+        entries
+            .len()
+            .saturating_mul(8_usize)
+            .saturating_add(4_usize),
+    );
+
+    #[expect(
+        clippy::little_endian_bytes,
+        reason = "The format of ACL extended attributes is little-endian"
+    )]
+    {
+        bytes.extend_from_slice(&ACL_EA_VERSION.to_le_bytes());
+
+        for (tag_value, id, permission_bits) in entries {
+            bytes.extend_from_slice(&tag_value.to_le_bytes());
+            bytes.extend_from_slice(&permission_bits.to_le_bytes());
+            bytes.extend_from_slice(&id.to_le_bytes());
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Decodes "%XX" escapes
+fn decode_percent(encoded: &str) -> anyhow::Result<String> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+
+    let mut iter = encoded.bytes();
+
+    while let Some(ut) = iter.next() {
+        if ut == b'%' {
+            let (Some(uti), Some(utf)) = (iter.next(), iter.next()) else {
+                anyhow::bail!("\"{encoded}\" ends with an incomplete escape");
+            };
+
+            let mut decoded = [0_u8; 1_usize];
+
+            hex::decode_to_slice([uti, utf], &mut decoded)?;
+
+            bytes.extend_from_slice(&decoded);
+        } else {
+            bytes.push(ut);
+        }
+    }
+
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Decodes standard base64. Padding is optional, and whitespace is ignored.
+fn decode_base64(encoded: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+
+    let mut buffer = 0_u32;
+    let mut bits = 0_u32;
+
+    for &ut in encoded {
+        let sextet = match ut {
+            b'A'..=b'Z' => ut.saturating_sub(b'A'),
+            b'a'..=b'z' => ut.saturating_sub(b'a').saturating_add(26_u8),
+            b'0'..=b'9' => ut.saturating_sub(b'0').saturating_add(52_u8),
+            b'+' => 62_u8,
+            b'/' => 63_u8,
+            b'=' | b' ' | b'\t' | b'\n' | b'\r' => {
+                continue;
+            }
+            _ => {
+                anyhow::bail!("Value is not valid base64");
+            }
+        };
+
+        // Bits that have already been decoded are shifted out
+        buffer = buffer.wrapping_shl(6_u32) | u32::from(sextet);
+        bits = bits.saturating_add(6_u32);
+
+        if bits >= 8_u32 {
+            bits = bits.saturating_sub(8_u32);
+
+            bytes.push(u8::try_from(buffer.wrapping_shr(bits) & 0xFF_u32)?);
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_base64, decode_percent, encode_acl};
+
+    #[test]
+    fn base64_padding() -> anyhow::Result<()> {
+        for (encoded, decoded) in [
+            (&b"dGFyeA=="[..], &b"tarx"[..]),
+            (b"dGFyeA", b"tarx"),
+            (b"dGFyeDE=", b"tarx1"),
+            (b"dGFyeDEy", b"tarx12"),
+            (b"dGFy\neDEy\n", b"tarx12"),
+            (b"", b""),
+        ] {
+            anyhow::ensure!(
+                decode_base64(encoded)? == decoded,
+                "\"{}\" was not decoded correctly",
+                String::from_utf8_lossy(encoded)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn base64_invalid_characters() {
+        for encoded in [&b"dGF*eA=="[..], b"dGFyeA==\0", "dGFy\u{e9}".as_bytes()] {
+            assert!(
+                decode_base64(encoded).is_err(),
+                "\"{}\" was decoded",
+                String::from_utf8_lossy(encoded)
+            );
+        }
+    }
+
+    #[test]
+    fn percent_escapes() -> anyhow::Result<()> {
+        anyhow::ensure!(decode_percent("user.tarx%3Dname%25")? == "user.tarx=name%");
+        anyhow::ensure!(decode_percent("user.plain")? == "user.plain");
+
+        Ok(())
+    }
+
+    #[test]
+    fn percent_invalid_escapes() {
+        // Truncated, not hexadecimal, and not UTF-8
+        for encoded in ["user.tarx%3", "user.tarx%", "user.tarx%zz", "user.tarx%FF"] {
+            assert!(
+                decode_percent(encoded).is_err(),
+                "\"{encoded}\" was decoded"
+            );
+        }
+    }
+
+    #[test]
+    fn acl_named_users_and_groups() -> anyhow::Result<()> {
+        // star appends the numeric ID to named entries, which is used even if the name does not exist on this system
+        let encoded = encode_acl(
+            "user::rw-,user:tarx-missing-user:r--:1000,group::r--\ngroup:tarx-missing-group:rw-:50,mask::rw-,other::---",
+        )?;
+
+        // Little-endian version, then the tag, permissions, and ID of each entry, sorted by tag and then by ID
+        let expected = [
+            2_u8, 0, 0, 0, //
+            0x01, 0, 6, 0, 0xFF, 0xFF, 0xFF, 0xFF, //
+            0x02, 0, 4, 0, 0xE8, 0x03, 0, 0, //
+            0x04, 0, 4, 0, 0xFF, 0xFF, 0xFF, 0xFF, //
+            0x08, 0, 6, 0, 50, 0, 0, 0, //
+            0x10, 0, 6, 0, 0xFF, 0xFF, 0xFF, 0xFF, //
+            0x20, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+
+        anyhow::ensure!(encoded == expected, "ACL was not encoded correctly");
+
+        Ok(())
+    }
+
+    /// Names are resolved on this system if the numeric ID is not stored
+    #[cfg(unix)]
+    #[test]
+    fn acl_names() -> anyhow::Result<()> {
+        let encoded = encode_acl("u:root:r-x,g:root:-w-")?;
+
+        anyhow::ensure!(
+            encoded.get(4..) == Some(&[0x02, 0, 5, 0, 0, 0, 0, 0, 0x08, 0, 2, 0, 0, 0, 0, 0][..]),
+            "ACL was not encoded correctly"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn acl_invalid_entries() {
+        for text in [
+            "user::rw-,tarx::r--",
+            "user:rw-",
+            "user:tarx-missing-user:r--",
+        ] {
+            assert!(encode_acl(text).is_err(), "\"{text}\" was encoded");
+        }
+    }
+
+    #[test]
+    fn acl_invalid_permissions() {
+        // Unknown characters, letters in the wrong position, and too few or too many positions
+        for permissions in ["rwz", "wr-", "x--", "rw", "rwx-", ""] {
+            let text = format!("user::{permissions}");
+
+            assert!(encode_acl(&text).is_err(), "\"{text}\" was encoded");
+        }
+    }
+}
@@ -1,20 +1,30 @@
 mod include_libforeign;
 
+use crate::interrupt;
 use foreign_calls::{
     raw_to_box, safe_convert_rar_to_tar, safe_decompress_bzip_two, safe_decompress_zstd,
     SafeConvertRarToTarWrapperResult, SafeDecompressBzipTwoResult, SafeDecompressZstdResult,
 };
 use std::str;
 
-pub fn convert_rar_to_tar(input: &mut [u8], password: Option<String>) -> anyhow::Result<Box<[u8]>> {
+/// `max_entry_size` and `max_size` limit the size of each entry and of the whole converted archive
+pub fn convert_rar_to_tar(
+    input: &mut [u8],
+    password: Option<String>,
+    max_entry_size: Option<u64>,
+    max_size: Option<u64>,
+) -> anyhow::Result<Box<[u8]>> {
     let SafeConvertRarToTarWrapperResult {
         data,
         foreign_call_result,
-    } = safe_convert_rar_to_tar(input, password)?;
+    } = safe_convert_rar_to_tar(input, password, max_entry_size, max_size)?;
 
     let data_box = raw_to_box(data)?;
     let error_message_box = raw_to_box(foreign_call_result.error_message)?;
 
+    // The foreign code fails when it is cancelled
+    interrupt::check()?;
+
     let status_code = foreign_call_result.status_code;
 
     let status_code_u_three_two = i128::from(status_code);
@@ -34,15 +44,18 @@ pub fn convert_rar_to_tar(input: &mut [u8], password: Option<String>) -> anyhow:
 
 // TODO
 // Duplication
-pub fn decompress_bzip_two(input: &mut [u8]) -> anyhow::Result<Box<[u8]>> {
+pub fn decompress_bzip_two(input: &mut [u8], max_size: Option<u64>) -> anyhow::Result<Box<[u8]>> {
     let SafeDecompressBzipTwoResult {
         data,
         foreign_call_result,
-    } = safe_decompress_bzip_two(input)?;
+    } = safe_decompress_bzip_two(input, max_size)?;
 
     let data_box = raw_to_box(data)?;
     let error_message_box = raw_to_box(foreign_call_result.error_message)?;
 
+    // The foreign code fails when it is cancelled
+    interrupt::check()?;
+
     let status_code = foreign_call_result.status_code;
 
     let status_code_u_three_two = i128::from(status_code);
@@ -62,15 +75,18 @@ pub fn decompress_bzip_two(input: &mut [u8]) -> anyhow::Result<Box<[u8]>> {
 
 // TODO
 // Duplication
-pub fn decompress_zstd(input: &mut [u8]) -> anyhow::Result<Box<[u8]>> {
+pub fn decompress_zstd(input: &mut [u8], max_size: Option<u64>) -> anyhow::Result<Box<[u8]>> {
     let SafeDecompressZstdResult {
         data,
         foreign_call_result,
-    } = safe_decompress_zstd(input)?;
+    } = safe_decompress_zstd(input, max_size)?;
 
     let data_box = raw_to_box(data)?;
     let error_message_box = raw_to_box(foreign_call_result.error_message)?;
 
+    // The foreign code fails when it is cancelled
+    interrupt::check()?;
+
     let status_code = foreign_call_result.status_code;
 
     let status_code_u_three_two = i128::from(status_code);
@@ -107,6 +123,7 @@ mod foreign_calls {
     use super::include_libforeign::{
         self, ConvertRarToTar, DecompressBzipTwo, DecompressZstd, FreePointerAndLength,
     };
+    use crate::interrupt;
     use std::{ffi::c_void, slice};
 
     type IncludeLibforeignPointerAndLength = include_libforeign::pointer_and_length;
@@ -153,6 +170,8 @@ mod foreign_calls {
     pub fn safe_convert_rar_to_tar(
         input: &mut [u8],
         password: Option<String>,
+        max_entry_size: Option<u64>,
+        max_size: Option<u64>,
     ) -> anyhow::Result<SafeConvertRarToTarWrapperResult> {
         let pointer_and_length = slice_to_raw(input)?;
 
@@ -164,8 +183,15 @@ mod foreign_calls {
         let password_pointer_and_length = slice_to_raw(&mut password_to_use_vec)?;
 
         // Safety: TODO, check with Miri
-        let convert_rar_to_tar_return_type =
-            unsafe { ConvertRarToTar(pointer_and_length, password_pointer_and_length) };
+        let convert_rar_to_tar_return_type = unsafe {
+            ConvertRarToTar(
+                pointer_and_length,
+                password_pointer_and_length,
+                interrupt::get_interrupted_pointer(),
+                limit_to_raw(max_entry_size),
+                limit_to_raw(max_size),
+            )
+        };
 
         let foreign_call_result = ForeignCallResult {
             error_message: ForeignAllocation::new(convert_rar_to_tar_return_type.b_error_message),
@@ -183,11 +209,18 @@ mod foreign_calls {
 
     pub fn safe_decompress_bzip_two(
         input: &mut [u8],
+        max_size: Option<u64>,
     ) -> anyhow::Result<SafeDecompressBzipTwoResult> {
         let pointer_and_length = slice_to_raw(input)?;
 
         // Safety: TODO, check with Miri
-        let decompress_bzip_two_return_type = unsafe { DecompressBzipTwo(pointer_and_length) };
+        let decompress_bzip_two_return_type = unsafe {
+            DecompressBzipTwo(
+                pointer_and_length,
+                interrupt::get_interrupted_pointer(),
+                limit_to_raw(max_size),
+            )
+        };
 
         let foreign_call_result = ForeignCallResult {
             error_message: ForeignAllocation::new(decompress_bzip_two_return_type.b_error_message),
@@ -200,11 +233,20 @@ mod foreign_calls {
         })
     }
 
-    pub fn safe_decompress_zstd(input: &mut [u8]) -> anyhow::Result<SafeDecompressZstdResult> {
+    pub fn safe_decompress_zstd(
+        input: &mut [u8],
+        max_size: Option<u64>,
+    ) -> anyhow::Result<SafeDecompressZstdResult> {
         let pointer_and_length = slice_to_raw(input)?;
 
         // Safety: TODO, check with Miri
-        let decompress_zstd_return_type = unsafe { DecompressZstd(pointer_and_length) };
+        let decompress_zstd_return_type = unsafe {
+            DecompressZstd(
+                pointer_and_length,
+                interrupt::get_interrupted_pointer(),
+                limit_to_raw(max_size),
+            )
+        };
 
         let foreign_call_result = ForeignCallResult {
             error_message: ForeignAllocation::new(decompress_zstd_return_type.b_error_message),
@@ -233,6 +275,11 @@ mod foreign_calls {
         })
     }
 
+    // The foreign code treats a negative limit as no limit
+    fn limit_to_raw(limit: Option<u64>) -> i64 {
+        limit.map_or(-1_i64, |ut| i64::try_from(ut).unwrap_or(i64::MAX))
+    }
+
     // Convert the allocation from a foreign allocation to a non-foreign allocation by copying the data
     pub fn raw_to_box(foreign_allocation: ForeignAllocation) -> anyhow::Result<Box<[u8]>> {
         let pointer_and_length = foreign_allocation.pointer_and_length;
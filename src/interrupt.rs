@@ -0,0 +1,161 @@
+use std::{
+    io::{self, BufRead, ErrorKind, Read, Seek, SeekFrom},
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+/// Set by the signal handler to the number of the signal that was received. An `AtomicI32` (rather than an
+/// `AtomicBool`) so that the foreign code can read it as a C `int`.
+static INTERRUPTED: AtomicI32 = AtomicI32::new(0_i32);
+
+/// Makes SIGINT and SIGTERM stop extraction at the next entry or chunk, instead of terminating the process
+/// immediately. This allows partially extracted entries to be removed. A second signal terminates the process
+/// immediately.
+pub fn install_handler() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::{mem, ptr};
+
+        extern "C" fn handle_signal(signal: libc::c_int) {
+            if INTERRUPTED.swap(signal, Ordering::SeqCst) != 0_i32 {
+                // Safety: `_exit` is async-signal-safe
+                unsafe {
+                    libc::_exit(128_i32.saturating_add(signal));
+                }
+            }
+        }
+
+        #[expect(
+            clippy::as_conversions,
+            clippy::fn_to_numeric_cast_any,
+            reason = "`sigaction` takes the handler as an integer"
+        )]
+        let handler = handle_signal as *const () as libc::sighandler_t;
+
+        // Safety: `sigaction` is a plain C struct, for which all zeros is valid
+        let mut sigaction = unsafe { mem::zeroed::<libc::sigaction>() };
+
+        sigaction.sa_sigaction = handler;
+
+        // Without `SA_RESTART`, a blocking read (e.g. of the answer to "--interactive") fails with `EINTR` when a
+        // signal is received, instead of waiting for input (see `read_line`)
+        sigaction.sa_flags = 0_i32;
+
+        for it in [libc::SIGINT, libc::SIGTERM] {
+            // Safety: `handle_signal` only performs atomic operations and calls `_exit`, which are async-signal-safe
+            let result = unsafe { libc::sigaction(it, &raw const sigaction, ptr::null_mut()) };
+
+            anyhow::ensure!(
+                result == 0_i32,
+                "Could not install signal handler: {}",
+                io::Error::last_os_error()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The exit status used when extraction was stopped by a signal: 128 + the number of the signal, like shells
+pub fn get_exit_status() -> Option<u8> {
+    let signal = INTERRUPTED.load(Ordering::SeqCst);
+
+    if signal == 0_i32 {
+        return None;
+    }
+
+    Some(u8::try_from(128_i32.saturating_add(signal)).unwrap_or(u8::MAX))
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst) != 0_i32
+}
+
+/// Returns an error if SIGINT or SIGTERM has been received. Called between entries.
+pub fn check() -> anyhow::Result<()> {
+    anyhow::ensure!(!is_interrupted(), "Interrupted");
+
+    Ok(())
+}
+
+/// Reads a line from standard input, without the line ending. Returns `None` at the end of standard input. Unlike
+/// `Stdin::read_line`, which retries reads interrupted by a signal, this fails once SIGINT or SIGTERM has been
+/// received, so that a prompt is not left waiting for input (in which case only a second signal would stop tarx,
+/// without removing what was extracted).
+pub fn read_line() -> anyhow::Result<Option<String>> {
+    let mut stdin_lock = io::stdin().lock();
+
+    let mut line = Vec::new();
+
+    loop {
+        check()?;
+
+        let buf = match stdin_lock.fill_buf() {
+            Ok(sl) => sl,
+            Err(er) if er.kind() == ErrorKind::Interrupted => continue,
+            Err(er) => return Err(er.into()),
+        };
+
+        if buf.is_empty() {
+            if line.is_empty() {
+                return Ok(None);
+            }
+
+            break;
+        }
+
+        let (consumed, is_line_complete) = match buf.iter().position(|&ut| ut == b'\n') {
+            Some(us) => (us.saturating_add(1_usize), true),
+            None => (buf.len(), false),
+        };
+
+        line.extend_from_slice(buf.get(..consumed).unwrap_or(buf));
+
+        stdin_lock.consume(consumed);
+
+        if is_line_complete {
+            break;
+        }
+    }
+
+    Ok(Some(
+        String::from_utf8_lossy(&line)
+            .trim_end_matches(['\n', '\r'])
+            .to_owned(),
+    ))
+}
+
+/// Passed to the foreign code, which stops when the value it points to is not zero
+#[cfg(feature = "foreign")]
+pub fn get_interrupted_pointer() -> *mut i32 {
+    INTERRUPTED.as_ptr()
+}
+
+/// Returns an error from `read` once SIGINT or SIGTERM has been received, so that large entries are not read to the
+/// end
+pub struct InterruptibleReader<R> {
+    reader: R,
+}
+
+impl<R> InterruptibleReader<R> {
+    pub fn new(reader: R) -> InterruptibleReader<R> {
+        InterruptibleReader { reader }
+    }
+}
+
+impl<R: Read> Read for InterruptibleReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Not `ErrorKind::Interrupted`, which causes the read to be retried
+        if is_interrupted() {
+            return Err(io::Error::other("Interrupted"));
+        }
+
+        self.reader.read(buf)
+    }
+}
+
+/// Lets `tar::Archive::entries_with_seek` skip the data of entries when they are only listed
+impl<R: Seek> Seek for InterruptibleReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.reader.seek(pos)
+    }
+}
@@ -1,18 +1,40 @@
+mod cat;
+mod extract;
 #[cfg(feature = "foreign")]
 mod foreign;
+mod interrupt;
+mod new_directory;
 
 use anyhow::Context;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use extract::{
+    limits::{self, LimitedReader, LimitedWriter, Limits},
+    overwrite::OverwritePolicy,
+    ownership::{self, OwnershipOptions},
+    sparse::{self, PaxSparseFile, SparseWriter},
+    special_file::{self, SpecialFile, SpecialFileKind},
+    timestamps::{self, EntryTimes},
+    transform::Transform,
+    xattr::{self, Xattr, XattrOptions},
+    EntryInfo, EntryKind, ExtractOptions, Extractor, Member,
+};
 use flate2::read::GzDecoder;
+use glob::Pattern;
+use interrupt::InterruptibleReader;
+use new_directory::{ExistingDirectoryMode, NewDirectory};
+use sevenz_rust::{Password, SevenZArchiveEntry, SevenZReader};
 use std::{
     env,
     fs::{self, File, OpenOptions},
     io::{self, BufReader, Cursor, Read, Write},
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, SystemTime},
 };
-use tar::Archive;
+use tar::{Archive, Entries, Entry, EntryType};
+use time::OffsetDateTime;
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
-use zip::{read::ZipFile, result::ZipError, ZipArchive};
+use zip::{extra_fields::ExtraField, read::ZipFile, result::ZipError, ZipArchive};
 
 #[cfg(feature = "dlmalloc")]
 #[global_allocator]
@@ -20,8 +42,17 @@ static GLOBAL_DLMALLOC: dlmalloc::GlobalDlmalloc = dlmalloc::GlobalDlmalloc;
 
 /// Extract a .7z, .rar, .tar, .tar.bz2, .tar.gz, .tar.xz, .tar.zst, or .zip file to a new directory
 #[derive(Parser)]
-#[command(author, version, about)]
+#[command(
+    about,
+    args_conflicts_with_subcommands = true,
+    author,
+    subcommand_negates_reqs = true,
+    version
+)]
 struct TarxArgs {
+    #[command(subcommand)]
+    command: Option<TarxCommand>,
+
     /// Password of the encrypted archive file to be processed
     #[arg(long = "password", short = 'p')]
     password: Option<String>,
@@ -34,9 +65,224 @@ struct TarxArgs {
     #[arg(long = "list-files", short = 'l')]
     list_files: bool,
 
+    /// Print the files, directories, and links that would be created (and their permissions), without writing
+    /// anything. Conflicts with existing files, skipped entries, and the total number of bytes that would be written
+    /// are also reported.
+    #[arg(conflicts_with = "list_files", long = "dry-run")]
+    dry_run: bool,
+
+    /// If the only top-level entry of the archive is a directory, extract the contents of that directory directly into
+    /// the new directory
+    #[arg(long = "smart-unpack", short = 's')]
+    smart_unpack: bool,
+
+    /// Remove the specified number of leading components from the path of each entry before extracting it. Entries
+    /// with no more than this number of components are not extracted.
+    #[arg(default_value_t = 0_usize, long = "strip-components", value_name = "N")]
+    strip_components: usize,
+
+    /// Only process entries whose path (or the path of a directory containing them) matches this glob pattern (e.g.
+    /// "*/include/**"). Can be passed multiple times.
+    #[arg(long = "include", value_name = "PATTERN", value_parser = Pattern::new)]
+    include: Vec<Pattern>,
+
+    /// Do not process entries whose path (or the path of a directory containing them) matches this glob pattern (e.g.
+    /// "**/*.pdb"). Can be passed multiple times.
+    #[arg(long = "exclude", value_name = "PATTERN", value_parser = Pattern::new)]
+    exclude: Vec<Pattern>,
+
+    /// Rewrite the path of each entry with a sed-style substitution (e.g. "s,^pkg-1.2/,pkg/,"). The regular
+    /// expression uses the syntax of the "regex" crate. In the replacement, "&" is the whole match, and "\1" to "\9"
+    /// are capture groups. Supported flags are "g" (replace all matches) and "i" (case-insensitive). Can be passed
+    /// multiple times, and the substitutions are applied in order. Directory paths end with "/".
+    #[arg(long = "transform", value_name = "EXPRESSION", value_parser = Transform::parse)]
+    transform: Vec<Transform>,
+
+    /// Extract all files directly into the new directory, discarding the directories containing them (like "unzip -j").
+    /// Directory entries are skipped. Files with the same name are handled according to the overwrite policy
+    /// ("--rename" adds a suffix).
+    #[arg(long = "junk-paths", short = 'j')]
+    junk_paths: bool,
+
+    /// Skip metadata created by macOS ("__MACOSX/", "._*" AppleDouble files, and ".DS_Store") and Windows ("Thumbs.db"
+    /// and "desktop.ini"). The number of skipped entries is reported at the end.
+    #[arg(long = "skip-os-metadata")]
+    skip_os_metadata: bool,
+
     /// Path of the archive file to be processed
-    #[arg(index = 1_usize)]
-    archive_file_path: String,
+    #[arg(index = 1_usize, required = true)]
+    archive_file_path: Option<String>,
+
+    /// Replace existing files
+    #[arg(group = "overwrite_policy", long = "overwrite")]
+    overwrite: bool,
+
+    /// Keep existing files, and do not extract the entries that would replace them
+    #[arg(group = "overwrite_policy", long = "skip-existing")]
+    skip_existing: bool,
+
+    /// Only replace existing files that are older than the entries that would replace them
+    #[arg(group = "overwrite_policy", long = "keep-newer")]
+    keep_newer: bool,
+
+    /// Extract entries that would replace existing files under a new name (e.g. "name (1).txt")
+    #[arg(group = "overwrite_policy", long = "rename")]
+    rename: bool,
+
+    /// Interactively choose what to do for each existing file
+    #[arg(group = "overwrite_policy", long = "interactive", short = 'i')]
+    interactive: bool,
+
+    /// Template for the name of the new directory (e.g. "{stem}-{date}"). "{stem}" is replaced with the file name of
+    /// the archive file without its extension, "{date}" with the current date (YYYY-MM-DD, UTC), and "{time}" with the
+    /// current time (HH-MM-SS, UTC).
+    #[arg(long = "dir-name", value_name = "TEMPLATE")]
+    dir_name: Option<String>,
+
+    /// If the new directory already exists, extract into it instead of failing. Existing files are handled according
+    /// to the overwrite policy ("--overwrite", "--skip-existing", etc.). Entries are written directly into the existing
+    /// directory. If extraction fails, the files and directories it created are removed.
+    #[arg(group = "existing_directory", long = "merge", short = 'm')]
+    merge: bool,
+
+    /// If the new directory already exists, extract to "name (1)", "name (2)", etc. instead of failing
+    #[arg(group = "existing_directory", long = "unique-dir", short = 'u')]
+    unique_dir: bool,
+
+    /// If extraction fails or is interrupted (by SIGINT or SIGTERM), keep the entries that were already extracted
+    /// instead of removing them. Without "--merge", they are kept in the hidden staging directory next to the new
+    /// directory.
+    #[arg(long = "keep-partial")]
+    keep_partial: bool,
+
+    /// Continue an extraction that did not finish (because the process was killed, or was interrupted with
+    /// "--keep-partial"), skipping the entries that were already extracted. Progress is recorded in a journal in the
+    /// directory entries are extracted to. Entries that were only partially extracted are extracted again.
+    #[arg(conflicts_with = "dry_run", long = "resume")]
+    resume: bool,
+
+    /// Extract even if the file system of the new directory does not appear to have enough free space. Without this
+    /// option, the total uncompressed size of the entries is compared with the free space before anything is extracted.
+    /// This is skipped for .tar.gz files, which would have to be decompressed twice to list their entries (unless they
+    /// are listed anyway for "--smart-unpack").
+    #[arg(long = "force")]
+    force: bool,
+
+    /// Stop extracting if the total size of the extracted entries would be more than this (e.g. "10G"). The suffixes
+    /// "K", "M", "G", and "T" are powers of 1,024.
+    #[arg(long = "max-total-size", value_name = "SIZE", value_parser = limits::parse_size)]
+    max_total_size: Option<u64>,
+
+    /// Stop extracting if an entry is larger than this (e.g. "1G")
+    #[arg(long = "max-entry-size", value_name = "SIZE", value_parser = limits::parse_size)]
+    max_entry_size: Option<u64>,
+
+    /// Stop extracting if the total size of the extracted entries would be more than this many times the size of the
+    /// archive file
+    #[arg(long = "max-ratio", value_name = "RATIO")]
+    max_ratio: Option<u64>,
+
+    /// Stop extracting if the archive has more than this number of entries
+    #[arg(long = "max-entries", value_name = "N")]
+    max_entries: Option<u64>,
+
+    /// Stop extracting if the path of an entry has more than this number of components
+    #[arg(long = "max-path-components", value_name = "N")]
+    max_path_components: Option<usize>,
+
+    /// Keep the setuid, setgid, and sticky bits of extracted files and directories. Without this option, they are
+    /// removed, and the number of entries they were removed from is reported at the end.
+    #[arg(long = "keep-special-bits")]
+    keep_special_bits: bool,
+
+    /// Create device nodes and FIFOs. Without this option, they are skipped, and the number of skipped entries is
+    /// reported at the end. Creating device nodes usually requires root, and is only possible for tar archives, which
+    /// store device numbers.
+    #[arg(long = "special-files")]
+    special_files: bool,
+
+    /// Set the owner and group of extracted entries to the ones stored in the archive. This is the default when running
+    /// as root. .7z and .zip files do not store owners, so only "--owner" and "--group" apply to them. .rar files only
+    /// store owners if they were created with "-ow", in which case user and group names that exist on this system are
+    /// preferred over the numeric IDs. Entries whose owner could not be set are reported at the end.
+    #[arg(group = "ownership", long = "same-owner")]
+    same_owner: bool,
+
+    /// Do not set the owner and group stored in the archive, so that extracted entries are owned by the user running
+    /// tarx. This is the default when not running as root.
+    #[arg(group = "ownership", long = "no-same-owner")]
+    no_same_owner: bool,
+
+    /// Set the owner of every extracted entry to this user (a name or a numeric ID)
+    #[arg(long = "owner", value_name = "USER", value_parser = ownership::parse_user)]
+    owner: Option<u32>,
+
+    /// Set the group of every extracted entry to this group (a name or a numeric ID)
+    #[arg(long = "group", value_name = "GROUP", value_parser = ownership::parse_group)]
+    group: Option<u32>,
+
+    /// File that maps user IDs stored in the archive to the user IDs to set instead. Each line contains two numeric
+    /// IDs (e.g. "1000 0"). IDs that are not in the map are kept, and reported at the end. Only applies when the owners
+    /// stored in the archive are set (see "--same-owner").
+    #[arg(long = "owner-map", value_name = "FILE")]
+    owner_map: Option<PathBuf>,
+
+    /// Like "--owner-map", but for group IDs
+    #[arg(long = "group-map", value_name = "FILE")]
+    group_map: Option<PathBuf>,
+
+    /// Restore the extended attributes stored in the archive, other than POSIX ACLs and SELinux labels (see "--acls"
+    /// and "--selinux"). Only tar archives store them. Attributes that could not be set (e.g. because the file system
+    /// does not support them) are reported at the end. With "--list-files", they are shown under each entry.
+    #[arg(long = "xattrs")]
+    xattrs: bool,
+
+    /// Restore the POSIX ACLs stored in the archive (like "--xattrs")
+    #[arg(long = "acls")]
+    acls: bool,
+
+    /// Restore the SELinux labels stored in the archive (like "--xattrs"). Setting them usually requires root.
+    #[arg(long = "selinux")]
+    selinux: bool,
+
+    /// Skip runs of zeros instead of writing them, so that files that are mostly zeros (e.g. disk images) take up less
+    /// space on file systems that support sparse files. Sparse files stored in tar archives are always written this
+    /// way.
+    #[arg(long = "sparse")]
+    sparse: bool,
+
+    /// Flush every extracted file and directory to disk before the new directory is moved into place, and the rename
+    /// afterwards, so that the extracted entries are complete if the system loses power after tarx exits. The time
+    /// this took is reported at the end.
+    #[arg(long = "sync")]
+    sync: bool,
+
+    /// Paths of entries in the archive file to be processed (passing the path of a directory processes everything in
+    /// it). If none are passed, all entries are processed.
+    #[arg(index = 2_usize, value_name = "MEMBER")]
+    members: Vec<String>,
+}
+
+#[derive(Subcommand)]
+enum TarxCommand {
+    /// Write the contents of one member of the archive file to standard output
+    Cat {
+        /// Password of the encrypted archive file to be processed
+        #[arg(long = "password", short = 'p')]
+        password: Option<String>,
+
+        /// Interactively enter the password of the encrypted archive file
+        #[arg(long = "type-password", short = 't')]
+        type_password: bool,
+
+        /// Path of the archive file to be processed
+        #[arg(index = 1_usize)]
+        archive_file_path: String,
+
+        /// Path of the entry in the archive file to be written to standard output
+        #[arg(index = 2_usize, value_name = "MEMBER")]
+        member: String,
+    },
 }
 
 // TODO
@@ -75,37 +321,101 @@ enum FileType {
 }
 
 #[allow(clippy::too_many_lines, reason = "Unimportant")]
-fn main() -> Result<(), i32> {
+fn main() -> ExitCode {
     // TODO
     env::set_var("RUST_BACKTRACE", "1");
 
     tracing_subscriber::registry()
-        .with(fmt::layer().pretty())
+        // Standard output is reserved for the contents written by "tarx cat"
+        .with(fmt::layer().pretty().with_writer(io::stderr))
         .init();
 
     let result = start();
 
     if let Err(er) = result {
+        // The error may have been caused by the interruption (e.g. a read that was stopped)
+        if let Some(ut) = interrupt::get_exit_status() {
+            tracing::warn!("Interrupted, stopped extraction");
+
+            return ExitCode::from(ut);
+        }
+
         tracing::error!(
             backtrace = %er.backtrace(),
             error = %er,
         );
 
-        return Err(1_i32);
+        return ExitCode::FAILURE;
     }
 
-    Ok(())
+    ExitCode::SUCCESS
 }
 
 fn start() -> anyhow::Result<()> {
     let TarxArgs {
+        acls,
         archive_file_path,
+        command,
+        dir_name,
+        dry_run,
+        exclude,
+        force,
+        group,
+        group_map,
+        include,
+        interactive,
+        junk_paths,
+        keep_newer,
+        keep_partial,
+        keep_special_bits,
         list_files,
+        max_path_components,
+        max_entries,
+        max_entry_size,
+        max_ratio,
+        max_total_size,
+        members,
+        merge,
+        no_same_owner,
+        overwrite,
+        owner,
+        owner_map,
         password,
+        rename,
+        resume,
+        same_owner,
+        selinux,
+        skip_existing,
+        skip_os_metadata,
+        smart_unpack,
+        sparse,
+        special_files,
+        strip_components,
+        sync,
+        transform,
         type_password,
+        unique_dir,
+        xattrs,
     } = TarxArgs::parse();
 
-    let path = Path::new(archive_file_path.as_str());
+    // The member to write to standard output, if the "cat" subcommand was used
+    let (archive_file_path_to_use, password_option, type_password_to_use, cat_member) =
+        match command {
+            Some(TarxCommand::Cat {
+                archive_file_path: st,
+                member,
+                password: op,
+                type_password: bo,
+            }) => (st, op, bo, Some(Member::new(member)?)),
+            None => (
+                archive_file_path.context("No archive file path was passed")?,
+                password,
+                type_password,
+                None,
+            ),
+        };
+
+    let path = Path::new(archive_file_path_to_use.as_str());
 
     let path_buf = fs::canonicalize(path)?;
 
@@ -153,13 +463,13 @@ fn start() -> anyhow::Result<()> {
 
     let password_to_use = match file_type {
         FileType::Rar | FileType::SevenZ | FileType::Zip =>
-            match (password, type_password) {
+            match (password_option, type_password_to_use) {
                 // No password
                 (None, false) => None,
                 // Typed password
                 (None, true) => {
                     io
-                        ::stdout()
+                        ::stderr()
                         .write_all(
                             b"Password (note that the terminal will be cleared after a password is entered):\n"
                         )?;
@@ -170,7 +480,7 @@ fn start() -> anyhow::Result<()> {
 
                     // Clear the terminal to hide the entered password
                     // https://stackoverflow.com/questions/34837011/how-to-clear-the-terminal-screen-in-rust-after-a-new-line-is-printed/34837038#34837038
-                    io::stdout().write_all(&[27_u8, b'[', b'2', b'J'])?;
+                    io::stderr().write_all(&[27_u8, b'[', b'2', b'J'])?;
 
                     let op = read_line.pop();
 
@@ -209,7 +519,7 @@ fn start() -> anyhow::Result<()> {
                     ),
             }
         FileType::Tar | FileType::TarBzTwo | FileType::TarGz | FileType::TarXz | FileType::TarZst=> {
-            match (password, type_password) {
+            match (password_option, type_password_to_use) {
                 // No password
                 (None, false) => None,
                 // Invalid
@@ -226,7 +536,87 @@ fn start() -> anyhow::Result<()> {
         anyhow::bail!("Listing files is not currently implemented for .7z and .zip files");
     }
 
-    let make_new_directory = || get_new_directory(file_name_str, extension);
+    // Listing files and "tarx cat" do not create anything that would have to be removed
+    if cat_member.is_none() && !list_files {
+        interrupt::install_handler()?;
+    }
+
+    let overwrite_policy = match (overwrite, skip_existing, keep_newer, rename, interactive) {
+        (true, _, _, _, _) => OverwritePolicy::Overwrite,
+        (_, true, _, _, _) => OverwritePolicy::SkipExisting,
+        (_, _, true, _, _) => OverwritePolicy::KeepNewer,
+        (_, _, _, true, _) => OverwritePolicy::Rename,
+        (_, _, _, _, true) => OverwritePolicy::Ask,
+        _ => OverwritePolicy::Error,
+    };
+
+    let extract_options = ExtractOptions {
+        dry_run,
+        exclude_patterns: exclude,
+        force,
+        include_patterns: include,
+        junk_paths,
+        keep_special_bits,
+        limits: Limits {
+            archive_size: fs::metadata(path_buf_path)?.len(),
+            max_path_components,
+            max_entries,
+            max_entry_size,
+            max_ratio,
+            max_total_size,
+        },
+        members: members
+            .into_iter()
+            .map(Member::new)
+            .collect::<anyhow::Result<_>>()?,
+        overwrite_policy,
+        ownership: OwnershipOptions {
+            group,
+            group_map: group_map
+                .as_deref()
+                .map(ownership::read_id_map)
+                .transpose()?
+                .unwrap_or_default(),
+            owner,
+            owner_map: owner_map
+                .as_deref()
+                .map(ownership::read_id_map)
+                .transpose()?
+                .unwrap_or_default(),
+            same_owner: same_owner || (!no_same_owner && ownership::is_root()),
+        },
+        resume,
+        skip_os_metadata,
+        smart_unpack,
+        sparse,
+        special_files,
+        strip_components,
+        sync,
+        transforms: transform,
+        xattr_options: XattrOptions {
+            acls,
+            selinux,
+            xattrs,
+        },
+    };
+
+    let existing_directory_mode = match (merge, unique_dir) {
+        (true, _) => ExistingDirectoryMode::Merge,
+        (_, true) => ExistingDirectoryMode::Unique,
+        _ => ExistingDirectoryMode::Fail,
+    };
+
+    let make_new_directory = || {
+        get_new_directory(
+            file_name_str,
+            extension,
+            dir_name.as_deref(),
+            existing_directory_mode,
+            dry_run,
+            keep_partial,
+            resume,
+        )
+    };
 
     let get_file = || File::open(path_buf_path);
 
@@ -238,24 +628,42 @@ fn start() -> anyhow::Result<()> {
             {
                 let mut vec = fs::read(path_buf_path)?;
 
-                let decompressed_box = foreign::convert_rar_to_tar(&mut vec, password_to_use)?;
+                let decompressed_box = foreign::convert_rar_to_tar(
+                    &mut vec,
+                    password_to_use,
+                    extract_options.limits.max_entry_size,
+                    extract_options.limits.get_max_decompressed_size(),
+                )?;
 
-                let cursor = Cursor::new(decompressed_box);
+                let get_archive = || {
+                    anyhow::Ok(Archive::new(InterruptibleReader::new(Cursor::new(
+                        decompressed_box.as_ref(),
+                    ))))
+                };
 
-                let mut archive = Archive::new(cursor);
+                if let Some(ref me) = cat_member {
+                    cat::write_to_stdout(|bu| cat::write_tar_member(get_archive, me, bu))?;
+                } else if list_files {
+                    report_rar_redirections(&mut get_archive()?)?;
 
-                if list_files {
                     // TODO
                     // Print FFI warning here, too
-                    list_archive(&mut archive)?;
+                    list_archive(&mut get_archive()?, &extract_options)?;
                 } else {
+                    report_rar_redirections(&mut get_archive()?)?;
+
                     tracing::warn!(
                         ".rar extraction uses FFI to Go code, and this integration is naive and all in-memory. Extraction will fail if your system does not have enough free memory to store the .rar file plus its decompressed contents."
                     );
 
-                    let new_directory = make_new_directory()?;
-
-                    archive.unpack(new_directory.as_path())?;
+                    // RAR archives only store owners if they were created with "-ow"
+                    extract_tar_archive(
+                        get_archive,
+                        &extract_options,
+                        make_new_directory,
+                        StoredOwners::Named,
+                        list_tar_archive(get_archive()?.entries_with_seek()?, &extract_options)?,
+                    )?;
                 }
             }
 
@@ -267,29 +675,84 @@ fn start() -> anyhow::Result<()> {
             }
         }
         FileType::SevenZ => {
-            let new_directory = make_new_directory()?;
+            let password_for_seven_z = match password_to_use {
+                Some(st) => Password::from(st.as_str()),
+                None => Password::empty(),
+            };
 
-            if let Some(st) = password_to_use {
-                sevenz_rust::decompress_file_with_password(
-                    path_buf_path,
-                    new_directory,
-                    st.as_str().into(),
-                )?;
+            let mut seven_z_reader = SevenZReader::open(path_buf_path, password_for_seven_z)?;
+
+            if let Some(ref me) = cat_member {
+                return cat::write_to_stdout(|bu| {
+                    cat::write_seven_z_member(&mut seven_z_reader, me, bu)
+                });
+            }
+
+            let root_directory = if extract_options.smart_unpack {
+                extract_options.find_root_directory(
+                    seven_z_reader
+                        .archive()
+                        .files
+                        .iter()
+                        .map(|se| (PathBuf::from(se.name()), se.is_directory())),
+                )
             } else {
-                sevenz_rust::decompress_file(path_buf_path, new_directory)?;
+                None
+            };
+
+            let extractor =
+                Extractor::new(&extract_options, make_new_directory()?, root_directory)?;
+
+            extractor.ensure_free_space(
+                seven_z_reader
+                    .archive()
+                    .files
+                    .iter()
+                    .filter(|se| extract_options.is_entry_selected(Path::new(se.name())))
+                    .fold(0_u64, |acc, se| acc.saturating_add(se.size())),
+            )?;
+
+            // `for_each_entries` requires the closure to return a `sevenz_rust::Error`, so the actual error is
+            // stored here
+            let mut error_option = None::<anyhow::Error>;
+
+            seven_z_reader.for_each_entries(|se, re| {
+                match extract_seven_z_entry(&extractor, se, re) {
+                    Ok(()) => Ok(true),
+                    Err(er) => {
+                        error_option = Some(er);
+
+                        // Stop processing entries
+                        Ok(false)
+                    }
+                }
+            })?;
+
+            if let Some(er) = error_option {
+                return Err(er);
             }
+
+            extractor.finish()?;
         }
         FileType::Tar => {
-            let path_buf_file_buf_reader = BufReader::new(get_file()?);
-
-            let mut archive = Archive::new(path_buf_file_buf_reader);
+            let get_archive = || {
+                anyhow::Ok(Archive::new(InterruptibleReader::new(BufReader::new(
+                    get_file()?,
+                ))))
+            };
 
-            if list_files {
-                list_archive(&mut archive)?;
+            if let Some(ref me) = cat_member {
+                cat::write_to_stdout(|bu| cat::write_tar_member(get_archive, me, bu))?;
+            } else if list_files {
+                list_archive(&mut get_archive()?, &extract_options)?;
             } else {
-                let new_directory = make_new_directory()?;
-
-                archive.unpack(new_directory.as_path())?;
+                extract_tar_archive(
+                    get_archive,
+                    &extract_options,
+                    make_new_directory,
+                    StoredOwners::All,
+                    list_tar_archive(get_archive()?.entries_with_seek()?, &extract_options)?,
+                )?;
             }
         }
         FileType::TarBzTwo => {
@@ -297,24 +760,35 @@ fn start() -> anyhow::Result<()> {
             {
                 let mut vec = fs::read(path_buf_path)?;
 
-                let decompressed_box = foreign::decompress_bzip_two(&mut vec)?;
-
-                let cursor = Cursor::new(decompressed_box);
+                let decompressed_box = foreign::decompress_bzip_two(
+                    &mut vec,
+                    extract_options.limits.get_max_decompressed_size(),
+                )?;
 
-                let mut archive = Archive::new(cursor);
+                let get_archive = || {
+                    anyhow::Ok(Archive::new(InterruptibleReader::new(Cursor::new(
+                        decompressed_box.as_ref(),
+                    ))))
+                };
 
-                if list_files {
+                if let Some(ref me) = cat_member {
+                    cat::write_to_stdout(|bu| cat::write_tar_member(get_archive, me, bu))?;
+                } else if list_files {
                     // TODO
                     // Print FFI warning here, too
-                    list_archive(&mut archive)?;
+                    list_archive(&mut get_archive()?, &extract_options)?;
                 } else {
                     tracing::warn!(
                         ".tar.bz2 extraction uses FFI to Go code, and this integration is naive and all in-memory. Extraction will fail if your system does not have enough free memory to store the .tar.bz2 file plus the decompressed .tar file."
                     );
 
-                    let new_directory = make_new_directory()?;
-
-                    archive.unpack(new_directory.as_path())?;
+                    extract_tar_archive(
+                        get_archive,
+                        &extract_options,
+                        make_new_directory,
+                        StoredOwners::All,
+                        list_tar_archive(get_archive()?.entries_with_seek()?, &extract_options)?,
+                    )?;
                 }
             }
 
@@ -327,16 +801,32 @@ fn start() -> anyhow::Result<()> {
         }
         FileType::TarGz => {
             // `GzDecoder` does already creates a `BufReader`
-            let gz_decoder = GzDecoder::new(get_file()?);
-
-            let mut archive = Archive::new(gz_decoder);
+            let get_archive = || {
+                anyhow::Ok(Archive::new(InterruptibleReader::new(GzDecoder::new(
+                    get_file()?,
+                ))))
+            };
 
-            if list_files {
-                list_archive(&mut archive)?;
+            if let Some(ref me) = cat_member {
+                cat::write_to_stdout(|bu| cat::write_tar_member(get_archive, me, bu))?;
+            } else if list_files {
+                list_archive(&mut get_archive()?, &extract_options)?;
             } else {
-                let new_directory = make_new_directory()?;
+                // Listing the entries would mean decompressing the archive twice, so it is only done for
+                // "--smart-unpack", and the free space is not checked otherwise
+                let tar_listing = if extract_options.smart_unpack {
+                    list_tar_archive(get_archive()?.entries()?, &extract_options)?
+                } else {
+                    TarListing::default()
+                };
 
-                archive.unpack(new_directory.as_path())?;
+                extract_tar_archive(
+                    get_archive,
+                    &extract_options,
+                    make_new_directory,
+                    StoredOwners::All,
+                    tar_listing,
+                )?;
             }
         }
         FileType::TarXz => {
@@ -362,20 +852,35 @@ fn start() -> anyhow::Result<()> {
             // Set capacity to some multiple of the file size
             let mut vec = Vec::<u8>::with_capacity(vec_capacity);
 
-            let mut path_buf_file_buf_reader = BufReader::new(path_buf_file);
-
-            lzma_rs::xz_decompress(&mut path_buf_file_buf_reader, &mut vec)?;
-
-            let cursor = Cursor::new(vec);
-
-            let mut archive = Archive::new(cursor);
+            let mut path_buf_file_buf_reader =
+                BufReader::new(InterruptibleReader::new(path_buf_file));
+
+            lzma_rs::xz_decompress(
+                &mut path_buf_file_buf_reader,
+                &mut LimitedWriter::new(
+                    &mut vec,
+                    extract_options.limits.get_max_decompressed_size(),
+                ),
+            )?;
+
+            let get_archive = || {
+                anyhow::Ok(Archive::new(InterruptibleReader::new(Cursor::new(
+                    vec.as_slice(),
+                ))))
+            };
 
-            if list_files {
-                list_archive(&mut archive)?;
+            if let Some(ref me) = cat_member {
+                cat::write_to_stdout(|bu| cat::write_tar_member(get_archive, me, bu))?;
+            } else if list_files {
+                list_archive(&mut get_archive()?, &extract_options)?;
             } else {
-                let new_directory = make_new_directory()?;
-
-                archive.unpack(new_directory.as_path())?;
+                extract_tar_archive(
+                    get_archive,
+                    &extract_options,
+                    make_new_directory,
+                    StoredOwners::All,
+                    list_tar_archive(get_archive()?.entries_with_seek()?, &extract_options)?,
+                )?;
             }
         }
         FileType::TarZst => {
@@ -383,24 +888,35 @@ fn start() -> anyhow::Result<()> {
             {
                 let mut vec = fs::read(path_buf_path)?;
 
-                let decompressed_box = foreign::decompress_zstd(&mut vec)?;
-
-                let cursor = Cursor::new(decompressed_box);
+                let decompressed_box = foreign::decompress_zstd(
+                    &mut vec,
+                    extract_options.limits.get_max_decompressed_size(),
+                )?;
 
-                let mut archive = Archive::new(cursor);
+                let get_archive = || {
+                    anyhow::Ok(Archive::new(InterruptibleReader::new(Cursor::new(
+                        decompressed_box.as_ref(),
+                    ))))
+                };
 
-                if list_files {
+                if let Some(ref me) = cat_member {
+                    cat::write_to_stdout(|bu| cat::write_tar_member(get_archive, me, bu))?;
+                } else if list_files {
                     // TODO
                     // Print FFI warning here, too
-                    list_archive(&mut archive)?;
+                    list_archive(&mut get_archive()?, &extract_options)?;
                 } else {
                     tracing::warn!(
                         ".tar.zst extraction uses FFI to Go code, and this integration is naive and all in-memory. Extraction will fail if your system does not have enough free memory to store the .tar.zst file plus the decompressed .tar file."
                     );
 
-                    let new_directory = make_new_directory()?;
-
-                    archive.unpack(new_directory.as_path())?;
+                    extract_tar_archive(
+                        get_archive,
+                        &extract_options,
+                        make_new_directory,
+                        StoredOwners::All,
+                        list_tar_archive(get_archive()?.entries_with_seek()?, &extract_options)?,
+                    )?;
                 }
             }
 
@@ -417,6 +933,17 @@ fn start() -> anyhow::Result<()> {
             // Adapted from https://github.com/zip-rs/zip2/blob/e3c81023a7ebedceaf287be98f3a10b5c1c18f8e/examples/extract.rs
             let mut zip_archive = ZipArchive::new(path_buf_file_buf_reader)?;
 
+            if let Some(ref me) = cat_member {
+                return cat::write_to_stdout(|bu| {
+                    cat::write_zip_member(
+                        &mut zip_archive,
+                        password_to_use.as_ref().map(String::as_bytes),
+                        me,
+                        bu,
+                    )
+                });
+            }
+
             #[expect(clippy::type_complexity, reason = "Unimportant")]
             let get_zip_file: Box<
                 dyn for<'a> Fn(
@@ -433,23 +960,61 @@ fn start() -> anyhow::Result<()> {
                 Box::new(|zi: &mut ZipArchive<BufReader<File>>, index: usize| zi.by_index(index))
             };
 
-            let new_directory = make_new_directory()?;
+            let root_directory = if extract_options.smart_unpack {
+                let mut entry_paths = Vec::<(PathBuf, bool)>::with_capacity(zip_archive.len());
+
+                // Reading the raw entries does not require a password
+                for us in 0_usize..zip_archive.len() {
+                    let zip_file = zip_archive.by_index_raw(us)?;
+
+                    if let Some(pa) = zip_file.enclosed_name() {
+                        entry_paths.push((pa, zip_file.is_dir()));
+                    }
+                }
+
+                extract_options.find_root_directory(entry_paths)
+            } else {
+                None
+            };
+
+            let extractor =
+                Extractor::new(&extract_options, make_new_directory()?, root_directory)?;
+
+            {
+                let mut total_size = 0_u64;
+
+                // The sizes are stored in the central directory
+                for us in 0_usize..zip_archive.len() {
+                    let zip_file = zip_archive.by_index_raw(us)?;
+
+                    if zip_file
+                        .enclosed_name()
+                        .is_some_and(|pa| extract_options.is_entry_selected(pa.as_path()))
+                    {
+                        total_size = total_size.saturating_add(zip_file.size());
+                    }
+                }
+
+                extractor.ensure_free_space(total_size)?;
+            }
 
             for us in 0_usize..zip_archive.len() {
-                let mut zip_file = get_zip_file(&mut zip_archive, us)?;
+                interrupt::check()?;
 
-                let enclosed_name = zip_file.enclosed_name();
+                let mut zip_file = get_zip_file(&mut zip_archive, us)?;
 
-                let Some(pa) = enclosed_name else {
-                    tracing::warn!(?enclosed_name, "Could not get name of contained file");
+                // `enclosed_name` is `None` for absolute paths and paths containing ".." components, which are then
+                // skipped by `get_destination_path`, like they are for other formats
+                let pa = zip_file
+                    .enclosed_name()
+                    .unwrap_or_else(|| PathBuf::from(zip_file.name()));
 
+                let Some(entry_destination_path_buf) =
+                    extractor.get_destination_path(pa.as_path(), zip_file.is_dir())?
+                else {
                     continue;
                 };
 
-                let destination_path_buf = new_directory.join(pa);
-
-                let destination_path = destination_path_buf.as_path();
-
                 {
                     let comment = zip_file.comment();
 
@@ -458,43 +1023,125 @@ fn start() -> anyhow::Result<()> {
                     }
                 }
 
+                extractor.create_parent_directory(entry_destination_path_buf.as_path())?;
+
+                let entry_times = get_zip_file_times(&zip_file);
+
+                let entry_info = EntryInfo {
+                    access_time: entry_times.access,
+                    crc32: Some(zip_file.crc32()),
+                    creation_time: entry_times.creation,
+                    gid: None,
+                    kind: if zip_file.is_dir() {
+                        EntryKind::Directory
+                    } else if zip_file.is_symlink() {
+                        let size = zip_file.size();
+
+                        EntryKind::Symlink(read_symlink_target(&mut zip_file, size, pa.as_path())?)
+                    } else if let Some(sp) =
+                        zip_file.unix_mode().and_then(SpecialFileKind::from_mode)
+                    {
+                        // Zip archives do not store device numbers
+                        EntryKind::Special(SpecialFile {
+                            device: None,
+                            kind: sp,
+                        })
+                    } else if let Some(pat) = get_zip_hard_link_target(&zip_file) {
+                        EntryKind::HardLink(pat)
+                    } else {
+                        EntryKind::File
+                    },
+                    mode: zip_file.unix_mode(),
+                    modification_time: entry_times.modification,
+                    size: zip_file.size(),
+                    uid: None,
+                    xattrs: Vec::new(),
+                };
+
+                let Some(destination_path_buf) =
+                    extractor.prepare_destination(entry_destination_path_buf, &entry_info)?
+                else {
+                    continue;
+                };
+
+                let destination_path = destination_path_buf.as_path();
+
                 if zip_file.is_dir() {
                     fs::create_dir_all(destination_path)?;
+                } else if let EntryKind::Symlink(ref pat) = entry_info.kind {
+                    extractor.create_symlink(destination_path, pat.as_path())?;
+                } else if let EntryKind::HardLink(ref pat) = entry_info.kind {
+                    let Some(link_destination_path_buf) =
+                        extractor.get_hard_link_target_path(pat.as_path())?
+                    else {
+                        tracing::warn!(
+                            entry_path = %pa.display(),
+                            link_name = %pat.display(),
+                            "Skipping hard link whose target is not extracted"
+                        );
+
+                        continue;
+                    };
+
+                    fs::hard_link(link_destination_path_buf, destination_path)?;
+                } else if let EntryKind::Special(ref sp) = entry_info.kind {
+                    special_file::create_special_file(
+                        destination_path,
+                        sp,
+                        extractor.get_mode(&entry_info),
+                    )?;
                 } else {
-                    if let Some(pat) = destination_path.parent() {
-                        if !pat.exists() {
-                            fs::create_dir_all(pat)?;
-                        }
-                    }
-
-                    let mut file = OpenOptions::new()
-                        .create_new(true)
-                        .write(true)
-                        .open(destination_path)?;
-
-                    io::copy(&mut zip_file, &mut file)?;
+                    // The size stored in the archive does not limit how much data is decompressed
+                    extractor.write_file(
+                        destination_path,
+                        &mut LimitedReader::new(
+                            InterruptibleReader::new(&mut zip_file),
+                            entry_info.size,
+                            pa.as_path(),
+                        ),
+                    )?;
                 }
 
-                #[cfg(unix)]
-                {
-                    use std::fs::Permissions;
-                    use std::os::unix::fs::PermissionsExt;
+                extractor.set_mode(destination_path, &entry_info)?;
 
-                    if let Some(ut) = zip_file.unix_mode() {
-                        fs::set_permissions(destination_path, Permissions::from_mode(ut))?;
-                    }
-                }
+                extractor.complete_entry(destination_path, &entry_info)?;
             }
+
+            extractor.finish()?;
         }
     }
 
+    // When extracting, this is checked before the new directory is moved into place
+    if list_files {
+        extract_options.ensure_members_found()?;
+    }
+
     Ok(())
 }
 
-fn get_new_directory(file_name: &str, extension: &str) -> anyhow::Result<PathBuf> {
+fn get_new_directory(
+    file_name: &str,
+    extension: &str,
+    directory_name_template: Option<&str>,
+    existing_directory_mode: ExistingDirectoryMode,
+    dry_run: bool,
+    keep_partial: bool,
+    resume: bool,
+) -> anyhow::Result<NewDirectory> {
     let file_name_without_extension = strip_extension(file_name, extension)?;
 
-    make_new_directory(file_name_without_extension)
+    let new_directory_name = match directory_name_template {
+        Some(st) => render_directory_name_template(st, file_name_without_extension)?,
+        None => file_name_without_extension.to_owned(),
+    };
+
+    new_directory::make_new_directory(
+        env::current_dir()?.join(new_directory_name),
+        existing_directory_mode,
+        dry_run,
+        keep_partial,
+        resume,
+    )
 }
 
 fn strip_extension<'a>(file_name: &'a str, extension: &str) -> anyhow::Result<&'a str> {
@@ -503,31 +1150,735 @@ fn strip_extension<'a>(file_name: &'a str, extension: &str) -> anyhow::Result<&'
         .context("Could not remove extension from file name")
 }
 
-fn make_new_directory(file_name_without_extension: &str) -> anyhow::Result<PathBuf> {
-    let path_buf = env::current_dir()?;
-
-    let new_directory_path_buf = path_buf.join(file_name_without_extension);
+/// Replaces "{stem}", "{date}", and "{time}" in `template`
+fn render_directory_name_template(
+    template: &str,
+    file_name_without_extension: &str,
+) -> anyhow::Result<String> {
+    let now = OffsetDateTime::now_utc();
+
+    let mut directory_name = String::with_capacity(template.len());
+
+    let mut rest = template;
+
+    while let Some((before, after)) = rest.split_once('{') {
+        directory_name.push_str(before);
+
+        let (placeholder, after_placeholder) = after
+            .split_once('}')
+            .context("Directory name template contains a \"{\" without a matching \"}\"")?;
+
+        match placeholder {
+            "date" => {
+                directory_name.push_str(
+                    format!(
+                        "{:04}-{:02}-{:02}",
+                        now.year(),
+                        u8::from(now.month()),
+                        now.day()
+                    )
+                    .as_str(),
+                );
+            }
+            "stem" => {
+                directory_name.push_str(file_name_without_extension);
+            }
+            "time" => {
+                directory_name.push_str(
+                    format!("{:02}-{:02}-{:02}", now.hour(), now.minute(), now.second()).as_str(),
+                );
+            }
+            _ => {
+                anyhow::bail!(
+                    "Unrecognized placeholder \"{{{placeholder}}}\" in directory name template. Supported placeholders are \"{{stem}}\", \"{{date}}\", and \"{{time}}\"."
+                );
+            }
+        }
 
-    #[expect(clippy::create_dir, reason = "Intentional")]
-    {
-        fs::create_dir(new_directory_path_buf.as_path())?;
+        rest = after_placeholder;
     }
 
-    Ok(new_directory_path_buf)
+    directory_name.push_str(rest);
+
+    let mut components = Path::new(directory_name.as_str()).components();
+
+    anyhow::ensure!(
+        matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ),
+        "\"{directory_name}\" is not a valid directory name"
+    );
+
+    Ok(directory_name)
 }
 
-fn list_archive<R: Read>(archive: &mut Archive<R>) -> anyhow::Result<()> {
+fn list_archive<R: Read>(
+    archive: &mut Archive<R>,
+    extract_options: &ExtractOptions,
+) -> anyhow::Result<()> {
     let entries = archive.entries()?;
 
     let mut stdout_lock = io::stdout().lock();
 
     for re in entries {
-        let entry = re?;
+        let mut entry = re?;
+
+        if extract::is_tar_metadata_entry(entry.header().entry_type()) {
+            continue;
+        }
+
+        let pax_sparse_file = PaxSparseFile::from_entry(&mut entry)?;
+
+        let entry_path = sparse::get_tar_entry_path(&entry, pax_sparse_file.as_ref())?;
+
+        if !extract_options.is_entry_selected(entry_path.as_path()) {
+            continue;
+        }
+
+        if extract_options.transforms.is_empty() && !extract_options.junk_paths {
+            writeln!(&mut stdout_lock, "{}", entry_path.display())?;
+        } else {
+            let is_directory = entry.header().entry_type().is_dir();
+
+            // Show where the entry would be extracted to
+            let transformed_entry_path_option = if extract_options.junk_paths && is_directory {
+                None
+            } else {
+                extract_options.get_transformed_entry_path(entry_path.as_path(), is_directory)
+            };
+
+            match transformed_entry_path_option {
+                Some(pa) if !pa.as_os_str().is_empty() => {
+                    writeln!(
+                        &mut stdout_lock,
+                        "{} -> {}",
+                        entry_path.display(),
+                        pa.display()
+                    )?;
+                }
+                _ => {
+                    writeln!(&mut stdout_lock, "{} (skipped)", entry_path.display())?;
+                }
+            }
+        }
+
+        if extract_options.xattr_options.is_enabled() {
+            for xa in get_tar_xattrs(&mut entry)? {
+                if extract_options.xattr_options.is_selected(&xa) {
+                    writeln!(
+                        &mut stdout_lock,
+                        "    {}={}",
+                        xa.get_name(),
+                        xa.get_display_value()
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the entries of a tar archive converted from a RAR archive that were links or copies of other entries, which
+/// the Go code marks with PAX records
+#[cfg(feature = "foreign")]
+fn report_rar_redirections<R: Read>(archive: &mut Archive<R>) -> anyhow::Result<()> {
+    const REDIRECTION_KEY: &str = "TARX.rar.redirection";
+    const REDIRECTION_TARGET_KEY: &str = "TARX.rar.redirection_target";
+
+    for re in archive.entries()? {
+        let mut entry = re?;
+
+        let Some(pax_extensions) = entry.pax_extensions()? else {
+            continue;
+        };
+
+        let mut kind = None;
+        let mut target = None;
+
+        for rei in pax_extensions {
+            let pax_extension = rei?;
+
+            match pax_extension.key()? {
+                REDIRECTION_KEY => {
+                    kind = Some(pax_extension.value()?.to_owned());
+                }
+                REDIRECTION_TARGET_KEY => {
+                    target = Some(pax_extension.value()?.to_owned());
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(st) = kind {
+            tracing::info!(
+                entry_path = %entry.path()?.display(),
+                kind = st,
+                target = target.unwrap_or_default(),
+                "RAR entry is a link or a copy of another entry"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_tar_archive<R: Read>(
+    get_archive: impl Fn() -> anyhow::Result<Archive<R>>,
+    extract_options: &ExtractOptions,
+    make_new_directory: impl FnOnce() -> anyhow::Result<NewDirectory>,
+    stored_owners: StoredOwners,
+    tar_listing: TarListing,
+) -> anyhow::Result<()> {
+    let root_directory = if extract_options.smart_unpack {
+        extract_options.find_root_directory(tar_listing.entry_paths)
+    } else {
+        None
+    };
+
+    let extractor = Extractor::new(extract_options, make_new_directory()?, root_directory)?;
+
+    match tar_listing.total_size {
+        Some(ut) => extractor.ensure_free_space(ut)?,
+        None if !(extract_options.force || extract_options.dry_run) => {
+            tracing::debug!(
+                "Not checking free space, since the compressed archive would have to be decompressed twice"
+            );
+        }
+        None => {}
+    }
+
+    let mut archive = get_archive()?;
+
+    // Like `tar::Archive::unpack`, directories are unpacked last, so that their permissions do not prevent their
+    // contents from being written
+    let mut directories = Vec::new();
+
+    for re in archive.entries()? {
+        interrupt::check()?;
+
+        let mut entry = re?;
+
+        if extract::is_tar_metadata_entry(entry.header().entry_type()) {
+            extractor.check_metadata_entry(entry.path()?.as_ref())?;
+
+            continue;
+        }
+
+        let pax_sparse_file = PaxSparseFile::from_entry(&mut entry)?;
+
+        let entry_path = sparse::get_tar_entry_path(&entry, pax_sparse_file.as_ref())?;
+
+        let Some(entry_destination_path_buf) = extractor
+            .get_destination_path(entry_path.as_path(), entry.header().entry_type().is_dir())?
+        else {
+            continue;
+        };
+
+        extractor.create_parent_directory(entry_destination_path_buf.as_path())?;
+
+        let entry_type = entry.header().entry_type();
+
+        let mut entry_info = get_tar_entry_info(
+            &mut entry,
+            stored_owners,
+            extract_options.xattr_options.is_enabled(),
+        )?;
+
+        if let Some(ref sp) = pax_sparse_file {
+            entry_info.size = sp.get_real_size();
+        }
+
+        let Some(destination_path_buf) =
+            extractor.prepare_destination(entry_destination_path_buf, &entry_info)?
+        else {
+            continue;
+        };
+
+        let destination_path = destination_path_buf.as_path();
+
+        // Otherwise only the permission bits are set, so the setuid, setgid, and sticky bits are removed
+        entry.set_preserve_permissions(extract_options.keep_special_bits);
+
+        if entry_type.is_dir() {
+            fs::create_dir_all(destination_path)?;
+
+            directories.push((entry, destination_path_buf, entry_info));
+
+            continue;
+        } else if entry_type.is_hard_link() {
+            // `tar::Entry::unpack` would resolve the link target relative to the current directory
+            let link_name = entry
+                .link_name()?
+                .context("Could not get target of hard link")?;
+
+            let Some(link_destination_path_buf) =
+                extractor.get_hard_link_target_path(link_name.as_ref())?
+            else {
+                tracing::warn!(
+                    entry_path = %entry_path.display(),
+                    link_name = %link_name.display(),
+                    "Skipping hard link whose target is not extracted"
+                );
+
+                continue;
+            };
+
+            fs::hard_link(link_destination_path_buf, destination_path)?;
+        } else if let EntryKind::Special(ref sp) = entry_info.kind {
+            // `tar::Entry::unpack` would create a regular file
+            special_file::create_special_file(
+                destination_path,
+                sp,
+                extractor.get_mode(&entry_info),
+            )?;
+        } else if let Some(ref sp) = pax_sparse_file {
+            // `tar::Entry::unpack` would write the sparse map and the regions as they are stored
+            let mut sparse_writer = SparseWriter::new(
+                OpenOptions::new()
+                    .create_new(true)
+                    .write(true)
+                    .open(destination_path)?,
+            );
+
+            sparse::copy_pax_sparse_file(&mut entry, &mut sparse_writer, sp)?;
+
+            sparse_writer.finish()?;
+
+            extractor.set_mode(destination_path, &entry_info)?;
+        } else if extract_options.sparse
+            && matches!(entry_info.kind, EntryKind::File)
+            && !entry_type.is_gnu_sparse()
+        {
+            // `tar::Entry::unpack` already skips the holes of sparse files in the old GNU format. Symlinks and entries of
+            // other types are left to it too.
+            extractor.write_file(destination_path, &mut entry)?;
+
+            extractor.set_mode(destination_path, &entry_info)?;
+        } else {
+            entry.unpack(destination_path)?;
+        }
+
+        extractor.complete_entry(destination_path, &entry_info)?;
+    }
+
+    // Deepest directories first
+    directories.sort_by(|&(_, ref pa, _), &(_, ref pat, _)| pat.cmp(pa));
+
+    for (mut entry, pa, entry_info) in directories {
+        interrupt::check()?;
+
+        // A symlink with the same path may have been extracted after the directory entry
+        extractor.ensure_inside_new_directory(pa.as_path())?;
+
+        entry.unpack(pa.as_path())?;
+
+        extractor.complete_entry(pa.as_path(), &entry_info)?;
+    }
+
+    extractor.finish()
+}
+
+/// The entries of a tar archive, listed before it is extracted (see `list_tar_archive`)
+#[derive(Default)]
+struct TarListing {
+    /// Only listed for "--smart-unpack"
+    entry_paths: Vec<(PathBuf, bool)>,
+    /// Total size of the selected entries. `None` if the free space is not checked.
+    total_size: Option<u64>,
+}
+
+/// Tar archives do not have an index, so their entries are listed before they are extracted if that is needed for
+/// "--smart-unpack" or the free space check. `entries` should seek over the data of the entries (see
+/// `tar::Archive::entries_with_seek`), unless the archive is compressed, in which case listing it means decompressing
+/// it twice.
+fn list_tar_archive<R: Read>(
+    entries: Entries<R>,
+    extract_options: &ExtractOptions,
+) -> anyhow::Result<TarListing> {
+    let check_free_space = !(extract_options.force || extract_options.dry_run);
+
+    let mut tar_listing = TarListing::default();
+
+    if !(extract_options.smart_unpack || check_free_space) {
+        return Ok(tar_listing);
+    }
+
+    let mut total_size = 0_u64;
+
+    for re in entries {
+        let mut entry = re?;
+
+        if extract::is_tar_metadata_entry(entry.header().entry_type()) {
+            continue;
+        }
+
+        let pax_sparse_file = PaxSparseFile::from_entry(&mut entry)?;
+
+        let entry_path = sparse::get_tar_entry_path(&entry, pax_sparse_file.as_ref())?;
+
+        if check_free_space && extract_options.is_entry_selected(entry_path.as_path()) {
+            total_size = total_size.saturating_add(
+                pax_sparse_file
+                    .as_ref()
+                    .map_or_else(|| entry.size(), PaxSparseFile::get_real_size),
+            );
+        }
+
+        if extract_options.smart_unpack {
+            tar_listing
+                .entry_paths
+                .push((entry_path, entry.header().entry_type().is_dir()));
+        }
+    }
+
+    if check_free_space {
+        tar_listing.total_size = Some(total_size);
+    }
+
+    Ok(tar_listing)
+}
+
+/// Which user and group IDs in the headers of a tar archive are meaningful
+#[derive(Clone, Copy)]
+enum StoredOwners {
+    All,
+    /// Only IDs that have a user or group name. Tar archives converted from RAR archives, which only store owners if
+    /// they were created with "-ow", have the name (or the ID, if no name was stored) set for IDs that are known.
+    #[cfg_attr(
+        not(feature = "foreign"),
+        expect(dead_code, reason = "Only used for RAR archives")
+    )]
+    Named,
+}
 
-        let entry_path = entry.path()?;
+/// Extended attributes are only read if `read_xattrs` is true
+fn get_tar_entry_info<R: Read>(
+    entry: &mut Entry<R>,
+    stored_owners: StoredOwners,
+    read_xattrs: bool,
+) -> anyhow::Result<EntryInfo> {
+    let xattrs = if read_xattrs {
+        get_tar_xattrs(entry)?
+    } else {
+        Vec::new()
+    };
+
+    let header = entry.header();
+
+    let entry_type = header.entry_type();
+
+    let get_link_name = || -> anyhow::Result<PathBuf> {
+        Ok(entry
+            .link_name()?
+            .context("Could not get target of link")?
+            .into_owned())
+    };
 
-        writeln!(&mut stdout_lock, "{}", entry_path.display())?;
+    let kind = if entry_type.is_dir() {
+        EntryKind::Directory
+    } else if entry_type.is_hard_link() {
+        EntryKind::HardLink(get_link_name()?)
+    } else if entry_type.is_symlink() {
+        EntryKind::Symlink(get_link_name()?)
+    } else if let Some(sp) = get_tar_special_file_kind(entry_type) {
+        EntryKind::Special(SpecialFile {
+            // Some implementations leave these fields empty for FIFOs
+            device: header
+                .device_major()
+                .ok()
+                .flatten()
+                .zip(header.device_minor().ok().flatten()),
+            kind: sp,
+        })
+    } else if entry_type.is_file() || entry_type.is_contiguous() || entry_type.is_gnu_sparse() {
+        EntryKind::File
+    } else {
+        EntryKind::Other
+    };
+
+    // Some implementations use IDs that do not fit into 32 bits to mean "unknown"
+    let get_id = |re: io::Result<u64>, name: Option<&[u8]>| {
+        re.ok()
+            .and_then(|ut| u32::try_from(ut).ok())
+            .filter(|_| match stored_owners {
+                StoredOwners::All => true,
+                StoredOwners::Named => name.is_some_and(|sl| !sl.is_empty()),
+            })
+    };
+
+    Ok(EntryInfo {
+        // `tar::Entry::unpack` sets the access time to the modification time
+        access_time: None,
+        // Tar archives do not store checksums of the contents
+        crc32: None,
+        creation_time: None,
+        gid: get_id(header.gid(), header.groupname_bytes()),
+        kind,
+        mode: header.mode().ok(),
+        modification_time: header
+            .mtime()
+            .ok()
+            .and_then(|us| SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(us))),
+        size: entry.size(),
+        uid: get_id(header.uid(), header.username_bytes()),
+        xattrs,
+    })
+}
+
+/// Reads the extended attributes, ACLs, and SELinux labels stored in the PAX extended header of an entry. Records that
+/// could not be parsed are skipped with a warning.
+fn get_tar_xattrs<R: Read>(entry: &mut Entry<R>) -> anyhow::Result<Vec<Xattr>> {
+    let mut xattrs = Vec::new();
+
+    let Some(pax_extensions) = entry.pax_extensions()? else {
+        return Ok(xattrs);
+    };
+
+    for re in pax_extensions {
+        let pax_extension = re?;
+
+        let key = pax_extension.key()?;
+
+        match xattr::parse_pax_record(key, pax_extension.value_bytes()) {
+            Ok(Some(xa)) => {
+                xattrs.push(xa);
+            }
+            Ok(None) => {}
+            Err(er) => {
+                tracing::warn!(
+                    key,
+                    error = %er,
+                    "Skipping extended attribute that could not be parsed"
+                );
+            }
+        }
     }
 
+    Ok(xattrs)
+}
+
+fn get_tar_special_file_kind(entry_type: EntryType) -> Option<SpecialFileKind> {
+    if entry_type.is_block_special() {
+        Some(SpecialFileKind::BlockDevice)
+    } else if entry_type.is_character_special() {
+        Some(SpecialFileKind::CharacterDevice)
+    } else if entry_type.is_fifo() {
+        Some(SpecialFileKind::Fifo)
+    } else {
+        None
+    }
+}
+
+fn extract_seven_z_entry(
+    extractor: &Extractor,
+    seven_z_archive_entry: &SevenZArchiveEntry,
+    reader: &mut dyn Read,
+) -> anyhow::Result<()> {
+    interrupt::check()?;
+
+    let mut interruptible_reader = InterruptibleReader::new(reader);
+
+    let mode = get_seven_z_entry_mode(seven_z_archive_entry);
+
+    let entry_path = Path::new(seven_z_archive_entry.name());
+
+    let entry_info = EntryInfo {
+        access_time: seven_z_archive_entry
+            .has_access_date
+            .then(|| seven_z_archive_entry.access_date().into()),
+        crc32: seven_z_archive_entry
+            .has_crc
+            .then(|| u32::try_from(seven_z_archive_entry.crc).ok())
+            .flatten(),
+        creation_time: seven_z_archive_entry
+            .has_creation_date
+            .then(|| seven_z_archive_entry.creation_date().into()),
+        gid: None,
+        kind: if seven_z_archive_entry.is_directory() {
+            EntryKind::Directory
+        } else if mode.is_some_and(special_file::is_symlink_mode) {
+            EntryKind::Symlink(read_symlink_target(
+                &mut interruptible_reader,
+                seven_z_archive_entry.size(),
+                entry_path,
+            )?)
+        } else if let Some(sp) = mode.and_then(SpecialFileKind::from_mode) {
+            // 7z archives do not store device numbers
+            EntryKind::Special(SpecialFile {
+                device: None,
+                kind: sp,
+            })
+        } else {
+            EntryKind::File
+        },
+        mode,
+        modification_time: seven_z_archive_entry
+            .has_last_modified_date
+            .then(|| seven_z_archive_entry.last_modified_date().into()),
+        size: seven_z_archive_entry.size(),
+        uid: None,
+        xattrs: Vec::new(),
+    };
+
+    let destination_path_buf_option =
+        match extractor.get_destination_path(entry_path, seven_z_archive_entry.is_directory())? {
+            Some(pa) => {
+                extractor.create_parent_directory(pa.as_path())?;
+
+                extractor.prepare_destination(pa, &entry_info)?
+            }
+            None => None,
+        };
+
+    let Some(destination_path_buf) = destination_path_buf_option else {
+        // The entries of a block are decompressed in order, so the data of skipped entries still has to be read
+        io::copy(&mut interruptible_reader, &mut io::sink())?;
+
+        return Ok(());
+    };
+
+    if let EntryKind::Special(ref sp) = entry_info.kind {
+        io::copy(&mut interruptible_reader, &mut io::sink())?;
+
+        special_file::create_special_file(
+            destination_path_buf.as_path(),
+            sp,
+            extractor.get_mode(&entry_info),
+        )?;
+    } else if seven_z_archive_entry.is_directory() {
+        fs::create_dir_all(destination_path_buf.as_path())?;
+    } else if let EntryKind::Symlink(ref pa) = entry_info.kind {
+        extractor.create_symlink(destination_path_buf.as_path(), pa.as_path())?;
+    } else {
+        extractor.write_file(
+            destination_path_buf.as_path(),
+            &mut LimitedReader::new(interruptible_reader, entry_info.size, entry_path),
+        )?;
+    }
+
+    extractor.complete_entry(destination_path_buf.as_path(), &entry_info)?;
+
     Ok(())
 }
+
+/// 7-Zip stores the Unix mode in the high 16 bits of the Windows attributes, and sets this flag when it does
+const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+
+fn get_seven_z_entry_mode(seven_z_archive_entry: &SevenZArchiveEntry) -> Option<u32> {
+    let windows_attributes = seven_z_archive_entry.windows_attributes();
+
+    (seven_z_archive_entry.has_windows_attributes
+        && windows_attributes & FILE_ATTRIBUTE_UNIX_EXTENSION != 0_u32)
+        .then_some(windows_attributes >> 16_u32)
+}
+
+/// Zip archives created on Unix (e.g. by Info-ZIP) and 7z archives created by p7zip store the target of a symlink as its
+/// contents
+fn read_symlink_target(reader: impl Read, size: u64, entry_path: &Path) -> anyhow::Result<PathBuf> {
+    let mut target = String::new();
+
+    LimitedReader::new(InterruptibleReader::new(reader), size, entry_path)
+        .read_to_string(&mut target)
+        .with_context(|| {
+            format!(
+                "Could not read the target of symlink \"{}\"",
+                entry_path.display()
+            )
+        })?;
+
+    Ok(PathBuf::from(target))
+}
+
+/// PKWARE's Unix extra field (0x000d) stores the target of a hard link after its fixed fields. Symlinks store their
+/// target there too, but are recognized by their mode. Info-ZIP does not store hard links.
+fn get_zip_hard_link_target(zip_file: &ZipFile) -> Option<PathBuf> {
+    const UNIX_EXTRA_FIELD_ID: u16 = 0x000d;
+    // Access time, modification time, UID, and GID
+    const FIXED_FIELDS_SIZE: usize = 12;
+
+    let mut extra_data = zip_file.extra_data()?;
+
+    #[expect(
+        clippy::little_endian_bytes,
+        reason = "The fields of zip extra fields are little-endian"
+    )]
+    while let Some((&id, after_id)) = extra_data.split_first_chunk::<2>() {
+        let (&size, after_size) = after_id.split_first_chunk::<2>()?;
+
+        let (field, after_field) =
+            after_size.split_at_checked(usize::from(u16::from_le_bytes(size)))?;
+
+        if u16::from_le_bytes(id) == UNIX_EXTRA_FIELD_ID {
+            let target = field.get(FIXED_FIELDS_SIZE..).filter(|sl| !sl.is_empty())?;
+
+            return Some(PathBuf::from(String::from_utf8_lossy(target).into_owned()));
+        }
+
+        extra_data = after_field;
+    }
+
+    None
+}
+
+/// The NTFS extra field is the most precise, followed by the extended timestamp extra field (0x5455), which stores Unix
+/// times. The DOS time is used if neither is present.
+fn get_zip_file_times(zip_file: &ZipFile) -> EntryTimes {
+    let from_unix_time =
+        |ut: u32| SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(u64::from(ut)));
+
+    for ex in zip_file.extra_data_fields() {
+        if let ExtraField::Ntfs(ref nt) = *ex {
+            if let Some(sy) = timestamps::from_windows_file_time(nt.mtime()) {
+                return EntryTimes {
+                    access: timestamps::from_windows_file_time(nt.atime()),
+                    creation: timestamps::from_windows_file_time(nt.ctime()),
+                    modification: Some(sy),
+                };
+            }
+        }
+    }
+
+    for ex in zip_file.extra_data_fields() {
+        if let ExtraField::ExtendedTimestamp(ref ext) = *ex {
+            if let Some(ut) = ext.mod_time() {
+                return EntryTimes {
+                    access: ext.ac_time().and_then(from_unix_time),
+                    creation: ext.cr_time().and_then(from_unix_time),
+                    modification: from_unix_time(ut),
+                };
+            }
+        }
+    }
+
+    EntryTimes {
+        access: None,
+        creation: None,
+        modification: get_zip_file_dos_time(zip_file),
+    }
+}
+
+/// The DOS time is the local time of the system that created the archive, which is assumed to be in the same time zone
+/// as this system
+fn get_zip_file_dos_time(zip_file: &ZipFile) -> Option<SystemTime> {
+    let date_time = zip_file.last_modified()?;
+
+    #[cfg(unix)]
+    {
+        timestamps::from_local_time(
+            date_time.year(),
+            date_time.month(),
+            date_time.day(),
+            date_time.hour(),
+            date_time.minute(),
+            date_time.second(),
+        )
+    }
+
+    // The local time zone is not known, so the time is interpreted as UTC
+    #[cfg(not(unix))]
+    {
+        Some(SystemTime::from(OffsetDateTime::try_from(date_time).ok()?))
+    }
+}
@@ -0,0 +1,515 @@
+use crate::extract::journal::JOURNAL_FILE_NAME;
+use anyhow::Context;
+use std::{
+    cell::RefCell,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process,
+    time::SystemTime,
+};
+
+/// What to do when the new directory already exists
+#[derive(Clone, Copy)]
+pub enum ExistingDirectoryMode {
+    Fail,
+    Merge,
+    Unique,
+}
+
+/// Where the entries of an archive are extracted to
+pub enum NewDirectory {
+    /// Nothing is written during a dry run, so the directory may not exist
+    DryRun(PathBuf),
+    /// Entries are extracted directly into an existing directory (with "--merge")
+    Existing(ExistingDirectory),
+    /// Entries are extracted into a staging directory, which is renamed to the new directory once every entry has been
+    /// extracted
+    Staged(StagingDirectory),
+}
+
+/// An existing directory that entries are extracted into. Files and directories created in it are removed when
+/// dropped, and files that entries replaced are restored, unless it was committed or `keep_partial` is set.
+pub struct ExistingDirectory {
+    committed: bool,
+    /// In the order they were created
+    created_paths: RefCell<Vec<PathBuf>>,
+    keep_partial: bool,
+    path: PathBuf,
+    /// Files that existed before extraction started and were replaced by entries, and where they were moved to
+    replaced_paths: RefCell<Vec<(PathBuf, PathBuf)>>,
+}
+
+/// A hidden directory next to the new directory. It is removed when dropped, unless it was committed or
+/// `keep_partial` is set.
+pub struct StagingDirectory {
+    committed: bool,
+    existing_directory_mode: ExistingDirectoryMode,
+    keep_partial: bool,
+    new_directory: PathBuf,
+    path: PathBuf,
+}
+
+impl NewDirectory {
+    /// The directory entries are written to
+    pub fn path(&self) -> &Path {
+        match *self {
+            NewDirectory::DryRun(ref pa) => pa.as_path(),
+            NewDirectory::Existing(ref ex) => ex.path.as_path(),
+            NewDirectory::Staged(ref st) => st.path.as_path(),
+        }
+    }
+
+    /// Records that `path` is about to be created (if it does not exist yet), so that it can be removed if extraction
+    /// fails. Only needed when extracting into an existing directory.
+    pub fn record_created(&self, path: &Path) {
+        if let NewDirectory::Existing(ref ex) = *self {
+            if fs::symlink_metadata(path).is_err() {
+                ex.created_paths.borrow_mut().push(path.to_path_buf());
+            }
+        }
+    }
+
+    /// Like `record_created`, for `directory` and each of its ancestors that does not exist yet
+    pub fn record_created_directories(&self, directory: &Path) {
+        if let NewDirectory::Existing(ref ex) = *self {
+            let mut missing_directories = directory
+                .ancestors()
+                .take_while(|pa| fs::symlink_metadata(pa).is_err())
+                .map(Path::to_path_buf)
+                .collect::<Vec<_>>();
+
+            missing_directories.reverse();
+
+            ex.created_paths.borrow_mut().extend(missing_directories);
+        }
+    }
+
+    /// Removes the existing file or symlink at `path`, so that an entry can replace it. When extracting into an
+    /// existing directory, a file that existed before extraction started is moved aside instead, so that it can be
+    /// restored if extraction fails.
+    pub fn remove_existing(&self, path: &Path) -> anyhow::Result<()> {
+        let NewDirectory::Existing(ref ex) = *self else {
+            fs::remove_file(path)?;
+
+            return Ok(());
+        };
+
+        // Entries extracted earlier (e.g. a second entry with the same path) have nothing to restore
+        if ex.created_paths.borrow().iter().any(|pa| pa == path) {
+            fs::remove_file(path)?;
+
+            return Ok(());
+        }
+
+        let backup_path = find_backup_path(path)?;
+
+        fs::rename(path, backup_path.as_path())?;
+
+        ex.replaced_paths
+            .borrow_mut()
+            .push((path.to_path_buf(), backup_path));
+
+        Ok(())
+    }
+
+    /// Called after every entry has been extracted. Returns the path of the new directory.
+    pub fn commit(self) -> anyhow::Result<PathBuf> {
+        match self {
+            NewDirectory::DryRun(pa) => Ok(pa),
+            NewDirectory::Existing(mut ex) => {
+                ex.committed = true;
+
+                ex.remove_backups();
+
+                Ok(ex.path.clone())
+            }
+            NewDirectory::Staged(st) => st.commit(),
+        }
+    }
+}
+
+impl ExistingDirectory {
+    /// The replaced files are not needed once the entries that replaced them are kept
+    fn remove_backups(&self) {
+        for &(_, ref pa) in self.replaced_paths.borrow().iter() {
+            if let Err(er) = fs::remove_file(pa) {
+                tracing::warn!(
+                    error = %er,
+                    path = %pa.display(),
+                    "Could not remove replaced file"
+                );
+            }
+        }
+    }
+}
+
+impl Drop for ExistingDirectory {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+
+        if self.keep_partial {
+            tracing::warn!(
+                created_entries = self.created_paths.get_mut().len(),
+                new_directory = %self.path.display(),
+                "Extraction did not finish, keeping the entries that were created"
+            );
+
+            self.remove_backups();
+
+            return;
+        }
+
+        let created_paths = self.created_paths.get_mut();
+
+        // Contents before the directories containing them
+        for pa in created_paths.iter().rev() {
+            let result = match fs::symlink_metadata(pa) {
+                Ok(me) if me.is_dir() => fs::remove_dir(pa),
+                Ok(_) => fs::remove_file(pa),
+                Err(_) => continue,
+            };
+
+            if let Err(er) = result {
+                tracing::warn!(
+                    error = %er,
+                    path = %pa.display(),
+                    "Could not remove partially extracted entry"
+                );
+            }
+        }
+
+        let replaced_paths = self.replaced_paths.get_mut();
+
+        // After the entries that replaced them were removed
+        for &(ref pa, ref pat) in replaced_paths.iter().rev() {
+            if let Err(er) = fs::rename(pat, pa) {
+                tracing::warn!(
+                    error = %er,
+                    backup_path = %pat.display(),
+                    path = %pa.display(),
+                    "Could not restore replaced file"
+                );
+            }
+        }
+
+        tracing::info!(
+            removed_entries = created_paths.len(),
+            restored_files = replaced_paths.len(),
+            "Extraction did not finish, removed the entries that were created and restored the files that were replaced"
+        );
+    }
+}
+
+impl StagingDirectory {
+    fn create(
+        new_directory: PathBuf,
+        existing_directory_mode: ExistingDirectoryMode,
+        keep_partial: bool,
+    ) -> anyhow::Result<StagingDirectory> {
+        let file_name = new_directory
+            .file_name()
+            .context("Could not get name of the new directory")?
+            .to_string_lossy()
+            .into_owned();
+
+        let process_id = process::id();
+
+        // A staging directory left behind by a process that was killed may have the same name
+        for ut in 0_u32..=u32::MAX {
+            let path = new_directory.with_file_name(format!(".{file_name}.tarx-{process_id}-{ut}"));
+
+            #[expect(clippy::create_dir, reason = "Intentional")]
+            match fs::create_dir(path.as_path()) {
+                Ok(()) => {
+                    return Ok(StagingDirectory {
+                        committed: false,
+                        existing_directory_mode,
+                        keep_partial,
+                        new_directory,
+                        path,
+                    });
+                }
+                Err(er) if er.kind() == ErrorKind::AlreadyExists => {}
+                Err(er) => {
+                    return Err(er.into());
+                }
+            }
+        }
+
+        anyhow::bail!("Could not find an unused name for the staging directory");
+    }
+
+    /// Renames the staging directory to the new directory
+    fn commit(mut self) -> anyhow::Result<PathBuf> {
+        // The new directory may have been created since extraction started
+        let new_directory = match self.existing_directory_mode {
+            ExistingDirectoryMode::Unique => find_unique_directory(self.new_directory.as_path())?,
+            ExistingDirectoryMode::Fail | ExistingDirectoryMode::Merge => {
+                anyhow::ensure!(
+                    fs::symlink_metadata(self.new_directory.as_path()).is_err(),
+                    "\"{}\" was created while the archive was being extracted",
+                    self.new_directory.display()
+                );
+
+                self.new_directory.clone()
+            }
+        };
+
+        fs::rename(self.path.as_path(), new_directory.as_path())?;
+
+        self.committed = true;
+
+        if new_directory != self.new_directory {
+            tracing::info!(
+                new_directory = %new_directory.display(),
+                "Directory already exists, extracted to a new directory with a unique name"
+            );
+        }
+
+        Ok(new_directory)
+    }
+}
+
+impl Drop for StagingDirectory {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+
+        if self.keep_partial {
+            tracing::warn!(
+                staging_directory = %self.path.display(),
+                "Extraction did not finish, keeping the partially extracted entries in the staging directory"
+            );
+
+            return;
+        }
+
+        if let Err(er) = remove_staging_directory(self.path.as_path()) {
+            tracing::warn!(
+                error = %er,
+                staging_directory = %self.path.display(),
+                "Could not remove staging directory"
+            );
+        }
+    }
+}
+
+/// Checks whether the new directory can be created (or extracted into), and creates the staging directory. During a
+/// dry run, nothing is created. If `keep_partial` is set, partially extracted entries are not removed when extraction
+/// fails. If `resume` is set, the directory left behind by an extraction that did not finish is reused.
+pub fn make_new_directory(
+    new_directory: PathBuf,
+    existing_directory_mode: ExistingDirectoryMode,
+    dry_run: bool,
+    keep_partial: bool,
+    resume: bool,
+) -> anyhow::Result<NewDirectory> {
+    if resume {
+        if let Some(ne) = find_unfinished_directory(
+            new_directory.as_path(),
+            existing_directory_mode,
+            keep_partial,
+        )? {
+            return Ok(ne);
+        }
+
+        tracing::warn!(
+            new_directory = %new_directory.display(),
+            "Could not find an unfinished extraction to resume, extracting from the beginning"
+        );
+    }
+
+    let exists = fs::symlink_metadata(new_directory.as_path()).is_ok();
+
+    match (exists, existing_directory_mode) {
+        (false, _) if dry_run => Ok(NewDirectory::DryRun(new_directory)),
+        (false, _) | (true, ExistingDirectoryMode::Unique) if !dry_run => Ok(NewDirectory::Staged(
+            StagingDirectory::create(new_directory, existing_directory_mode, keep_partial)?,
+        )),
+        (true, ExistingDirectoryMode::Merge) if new_directory.is_dir() => {
+            tracing::info!(
+                new_directory = %new_directory.display(),
+                "Extracting into existing directory"
+            );
+
+            if dry_run {
+                return Ok(NewDirectory::DryRun(new_directory));
+            }
+
+            Ok(NewDirectory::Existing(ExistingDirectory {
+                committed: false,
+                created_paths: RefCell::new(Vec::new()),
+                keep_partial,
+                path: new_directory,
+                replaced_paths: RefCell::new(Vec::new()),
+            }))
+        }
+        (true, ExistingDirectoryMode::Unique) => {
+            let unique_directory = find_unique_directory(new_directory.as_path())?;
+
+            tracing::info!(
+                new_directory = %unique_directory.display(),
+                "Directory already exists, extracting to a new directory with a unique name"
+            );
+
+            Ok(NewDirectory::DryRun(unique_directory))
+        }
+        _ => {
+            anyhow::bail!(
+                "\"{}\" already exists. Use \"--merge\"/\"-m\" to extract into it, or \"--unique-dir\"/\"-u\" to extract to a new directory with a unique name.",
+                new_directory.display()
+            );
+        }
+    }
+}
+
+/// Finds the directory an extraction that did not finish was writing to: the new directory itself if it was extracted
+/// into directly (with "--merge"), otherwise the most recently updated staging directory of the new directory.
+/// Directories without a journal are ignored.
+fn find_unfinished_directory(
+    new_directory: &Path,
+    existing_directory_mode: ExistingDirectoryMode,
+    keep_partial: bool,
+) -> anyhow::Result<Option<NewDirectory>> {
+    if new_directory.join(JOURNAL_FILE_NAME).is_file() {
+        tracing::info!(
+            new_directory = %new_directory.display(),
+            "Resuming extraction into existing directory"
+        );
+
+        return Ok(Some(NewDirectory::Existing(ExistingDirectory {
+            committed: false,
+            created_paths: RefCell::new(Vec::new()),
+            keep_partial,
+            path: new_directory.to_path_buf(),
+            replaced_paths: RefCell::new(Vec::new()),
+        })));
+    }
+
+    let (Some(parent), Some(file_name)) = (new_directory.parent(), new_directory.file_name())
+    else {
+        return Ok(None);
+    };
+
+    // See `StagingDirectory::create`
+    let staging_directory_prefix = format!(".{}.tarx-", file_name.to_string_lossy());
+
+    let mut latest_option = None::<(SystemTime, PathBuf)>;
+
+    for re in fs::read_dir(parent)? {
+        let dir_entry = re?;
+
+        if !dir_entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(staging_directory_prefix.as_str())
+        {
+            continue;
+        }
+
+        let path = dir_entry.path();
+
+        let Ok(modified) = fs::metadata(path.join(JOURNAL_FILE_NAME)).and_then(|me| me.modified())
+        else {
+            continue;
+        };
+
+        if latest_option.as_ref().is_none_or(|&(sy, _)| modified > sy) {
+            latest_option = Some((modified, path));
+        }
+    }
+
+    let Some((_, path)) = latest_option else {
+        return Ok(None);
+    };
+
+    tracing::info!(
+        staging_directory = %path.display(),
+        "Resuming extraction into staging directory"
+    );
+
+    Ok(Some(NewDirectory::Staged(StagingDirectory {
+        committed: false,
+        existing_directory_mode,
+        keep_partial,
+        new_directory: new_directory.to_path_buf(),
+        path,
+    })))
+}
+
+/// Returns `new_directory` if it does not exist, otherwise the first of "name (1)", "name (2)", etc. that does not
+fn find_unique_directory(new_directory: &Path) -> anyhow::Result<PathBuf> {
+    if fs::symlink_metadata(new_directory).is_err() {
+        return Ok(new_directory.to_path_buf());
+    }
+
+    let file_name = new_directory
+        .file_name()
+        .context("Could not get name of the new directory")?
+        .to_string_lossy();
+
+    (1_u32..=u32::MAX)
+        .map(|ut| new_directory.with_file_name(format!("{file_name} ({ut})")))
+        .find(|pa| fs::symlink_metadata(pa).is_err())
+        .context("Could not find a unique directory name")
+}
+
+/// A hidden path next to `path` that does not exist yet, where a replaced file is kept until extraction finishes (see
+/// `StagingDirectory::create`)
+fn find_backup_path(path: &Path) -> anyhow::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .context("Could not get file name of replaced file")?
+        .to_string_lossy()
+        .into_owned();
+
+    let process_id = process::id();
+
+    (0_u32..=u32::MAX)
+        .map(|ut| path.with_file_name(format!(".{file_name}.tarx-replaced-{process_id}-{ut}")))
+        .find(|pa| fs::symlink_metadata(pa).is_err())
+        .context("Could not find an unused name for the replaced file")
+}
+
+/// Removes the staging directory after a failed extraction. Directories extracted without write permission are made
+/// writable first, so that their contents can be removed.
+fn remove_staging_directory(path: &Path) -> anyhow::Result<()> {
+    if fs::remove_dir_all(path).is_ok() {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    {
+        make_directories_writable(path)?;
+    }
+
+    fs::remove_dir_all(path)?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn make_directories_writable(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = fs::symlink_metadata(path)?;
+
+    if !metadata.is_dir() {
+        return Ok(());
+    }
+
+    fs::set_permissions(
+        path,
+        fs::Permissions::from_mode(metadata.permissions().mode() | 0o700_u32),
+    )?;
+
+    for re in fs::read_dir(path)? {
+        make_directories_writable(re?.path().as_path())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! Writes single members to stdout with "tarx cat"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory, CONTENTS};
+use std::process::{Command, Stdio};
+
+/// Only the member is written to stdout, and nothing is extracted
+fn check_cat(format: Format) -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new(format!("cat-{}", format.extension()).as_str())?;
+
+    let archive_path =
+        test_directory.write_format_archive(format, &["dir/", "dir/file", "dir/other"])?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tarx"))
+        .arg("cat")
+        .arg(archive_path.as_path())
+        .arg("dir/file")
+        .current_dir(test_directory.path.as_path())
+        .output()?;
+
+    anyhow::ensure!(
+        output.status.success(),
+        "tarx failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    anyhow::ensure!(
+        output.stdout == CONTENTS,
+        "Member was not written to stdout"
+    );
+
+    common::ensure_missing(test_directory.extracted("").as_path())?;
+
+    // Directories and missing members are an error
+    for st in ["dir", "missing"] {
+        let failed_output = Command::new(env!("CARGO_BIN_EXE_tarx"))
+            .arg("cat")
+            .arg(archive_path.as_path())
+            .arg(st)
+            .current_dir(test_directory.path.as_path())
+            .output()?;
+
+        anyhow::ensure!(
+            !failed_output.status.success() && failed_output.stdout.is_empty(),
+            "\"{st}\" was written to stdout"
+        );
+    }
+
+    Ok(())
+}
+
+/// The reader of stdout closing it early is not an error
+fn check_cat_closed_stdout(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("cat-closed-stdout-{}", format.extension()).as_str())?;
+
+    // Larger than a pipe's buffer, so that writing it fails after the pipe is closed
+    let contents = vec![b'a'; 4 * 1024 * 1024];
+
+    let archive_path = test_directory.write_archive(
+        format!("archive.{}", format.extension()).as_str(),
+        &format.build_files(&[("file", contents.as_slice())])?,
+    )?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tarx"))
+        .arg("cat")
+        .arg(archive_path.as_path())
+        .arg("file")
+        .current_dir(test_directory.path.as_path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    drop(child.stdout.take());
+
+    let output = child.wait_with_output()?;
+
+    anyhow::ensure!(
+        output.status.success(),
+        "tarx failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_cat() -> anyhow::Result<()> {
+    check_cat(Format::SevenZ)
+}
+
+#[test]
+fn seven_z_cat_closed_stdout() -> anyhow::Result<()> {
+    check_cat_closed_stdout(Format::SevenZ)
+}
+
+#[test]
+fn tar_cat() -> anyhow::Result<()> {
+    check_cat(Format::Tar)
+}
+
+#[test]
+fn tar_cat_closed_stdout() -> anyhow::Result<()> {
+    check_cat_closed_stdout(Format::Tar)
+}
+
+#[test]
+fn zip_cat() -> anyhow::Result<()> {
+    check_cat(Format::Zip)
+}
+
+#[test]
+fn zip_cat_closed_stdout() -> anyhow::Result<()> {
+    check_cat_closed_stdout(Format::Zip)
+}
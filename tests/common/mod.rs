@@ -0,0 +1,293 @@
+//! Helpers shared by the integration tests
+
+#![allow(dead_code, reason = "Each test only uses some of the helpers")]
+
+use anyhow::Context;
+use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+use std::{
+    env, fs,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Output},
+};
+use tar::{EntryType, Header};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+pub const CONTENTS: &[u8] = b"contents\n";
+
+/// The formats that can be built without external tools
+#[derive(Clone, Copy)]
+pub enum Format {
+    SevenZ,
+    Tar,
+    Zip,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::SevenZ => "7z",
+            Format::Tar => "tar",
+            Format::Zip => "zip",
+        }
+    }
+
+    /// An archive containing the given paths. Paths ending with "/" are directories, and the other paths are files
+    /// containing `CONTENTS`.
+    pub fn build(self, paths: &[&str]) -> anyhow::Result<Vec<u8>> {
+        self.build_entries(
+            paths
+                .iter()
+                .map(|&st| match st.strip_suffix('/') {
+                    Some(sta) => (sta, None),
+                    None => (st, Some(CONTENTS)),
+                })
+                .collect::<Vec<_>>()
+                .as_slice(),
+        )
+    }
+
+    /// An archive containing files with the given paths and contents
+    pub fn build_files(self, files: &[(&str, &[u8])]) -> anyhow::Result<Vec<u8>> {
+        self.build_entries(
+            files
+                .iter()
+                .map(|&(st, sl)| (st, Some(sl)))
+                .collect::<Vec<_>>()
+                .as_slice(),
+        )
+    }
+
+    /// Entries without contents are directories
+    fn build_entries(self, entries: &[(&str, Option<&[u8]>)]) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Format::SevenZ => build_seven_z(entries),
+            Format::Tar => build_tar(entries),
+            Format::Zip => build_zip(entries),
+        }
+    }
+}
+
+/// A directory containing the archive and the new directory. Removed when dropped.
+pub struct TestDirectory {
+    pub path: PathBuf,
+}
+
+impl TestDirectory {
+    pub fn new(name: &str) -> anyhow::Result<TestDirectory> {
+        let path = env::temp_dir().join(format!("tarx-test-{name}-{}", process::id()));
+
+        if path.exists() {
+            fs::remove_dir_all(path.as_path())?;
+        }
+
+        fs::create_dir_all(path.as_path())?;
+
+        Ok(TestDirectory { path })
+    }
+
+    pub fn write_archive(&self, file_name: &str, bytes: &[u8]) -> anyhow::Result<PathBuf> {
+        let archive_path = self.path.join(file_name);
+
+        fs::write(archive_path.as_path(), bytes)?;
+
+        Ok(archive_path)
+    }
+
+    /// Writes "archive.<extension>" containing the given paths (see `Format::build`)
+    pub fn write_format_archive(&self, format: Format, paths: &[&str]) -> anyhow::Result<PathBuf> {
+        self.write_archive(
+            format!("archive.{}", format.extension()).as_str(),
+            &format.build(paths)?,
+        )
+    }
+
+    /// Path of an extracted entry, in the new directory "archive"
+    pub fn extracted(&self, entry_path: &str) -> PathBuf {
+        self.path.join("archive").join(entry_path)
+    }
+
+    /// `args` are passed after the archive, so that they can include members. The new directory is created in the
+    /// current directory. Fails if tarx fails.
+    pub fn run_tarx(&self, archive_path: &Path, args: &[&str]) -> anyhow::Result<Output> {
+        let output = self.run_tarx_unchecked(archive_path, args)?;
+
+        anyhow::ensure!(
+            output.status.success(),
+            "tarx failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        Ok(output)
+    }
+
+    /// Fails if tarx succeeds. Returns what tarx wrote to stdout and stderr, which contain the error.
+    pub fn run_tarx_failing(&self, archive_path: &Path, args: &[&str]) -> anyhow::Result<String> {
+        let output = self.run_tarx_unchecked(archive_path, args)?;
+
+        anyhow::ensure!(!output.status.success(), "tarx did not fail");
+
+        Ok(format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+
+    pub fn run_tarx_unchecked(&self, archive_path: &Path, args: &[&str]) -> anyhow::Result<Output> {
+        Ok(Command::new(env!("CARGO_BIN_EXE_tarx"))
+            .arg(archive_path)
+            .args(args)
+            .current_dir(self.path.as_path())
+            .output()?)
+    }
+}
+
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        fs::remove_dir_all(self.path.as_path()).ok();
+    }
+}
+
+/// Reads an archive checked into "tests/fixtures", for formats that cannot be built without external tools
+pub fn read_fixture(file_name: &str) -> anyhow::Result<Vec<u8>> {
+    Ok(fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(file_name),
+    )?)
+}
+
+/// Writes the header fields directly, since `tar::Header::set_path` rejects some of the paths being tested
+pub fn append_tar_entry(
+    builder: &mut tar::Builder<Vec<u8>>,
+    path: &[u8],
+    entry_type: EntryType,
+    link_name: Option<&[u8]>,
+    data: &[u8],
+) -> anyhow::Result<()> {
+    let mut header = Header::new_gnu();
+
+    header
+        .as_old_mut()
+        .name
+        .get_mut(..path.len())
+        .context("Path is too long")?
+        .copy_from_slice(path);
+
+    if let Some(sl) = link_name {
+        header.set_link_name_literal(sl)?;
+    }
+
+    header.set_entry_type(entry_type);
+    header.set_mode(if entry_type.is_dir() { 0o755 } else { 0o644 });
+    header.set_size(u64::try_from(data.len())?);
+    header.set_cksum();
+
+    builder.append(&header, data)?;
+
+    Ok(())
+}
+
+/// Stores the target as the contents of the entry, and the Unix mode in the high 16 bits of the Windows attributes, like
+/// p7zip does
+pub fn push_seven_z_symlink(
+    seven_z_writer: &mut SevenZWriter<Cursor<Vec<u8>>>,
+    path: &str,
+    target: &str,
+) -> anyhow::Result<()> {
+    const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+
+    let mut seven_z_archive_entry = SevenZArchiveEntry::new();
+
+    path.clone_into(&mut seven_z_archive_entry.name);
+    seven_z_archive_entry.has_windows_attributes = true;
+    seven_z_archive_entry.windows_attributes =
+        (0o120_777 << 16_u32) | FILE_ATTRIBUTE_UNIX_EXTENSION;
+
+    seven_z_writer.push_archive_entry(seven_z_archive_entry, Some(target.as_bytes()))?;
+
+    Ok(())
+}
+
+fn build_seven_z(entries: &[(&str, Option<&[u8]>)]) -> anyhow::Result<Vec<u8>> {
+    let mut seven_z_writer = SevenZWriter::new(Cursor::new(Vec::new()))?;
+
+    for &(st, op) in entries {
+        let mut seven_z_archive_entry = SevenZArchiveEntry::new();
+
+        st.clone_into(&mut seven_z_archive_entry.name);
+        seven_z_archive_entry.is_directory = op.is_none();
+
+        seven_z_writer.push_archive_entry(seven_z_archive_entry, op)?;
+    }
+
+    Ok(seven_z_writer.finish()?.into_inner())
+}
+
+fn build_tar(entries: &[(&str, Option<&[u8]>)]) -> anyhow::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+
+    for &(st, op) in entries {
+        match op {
+            Some(sl) => {
+                append_tar_entry(&mut builder, st.as_bytes(), EntryType::Regular, None, sl)?;
+            }
+            None => {
+                append_tar_entry(
+                    &mut builder,
+                    format!("{st}/").as_bytes(),
+                    EntryType::Directory,
+                    None,
+                    &[],
+                )?;
+            }
+        }
+    }
+
+    Ok(builder.into_inner()?)
+}
+
+fn build_zip(entries: &[(&str, Option<&[u8]>)]) -> anyhow::Result<Vec<u8>> {
+    let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    let simple_file_options =
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    for &(st, op) in entries {
+        match op {
+            Some(sl) => {
+                zip_writer.start_file(st, simple_file_options)?;
+                zip_writer.write_all(sl)?;
+            }
+            None => {
+                zip_writer.add_directory(st, simple_file_options)?;
+            }
+        }
+    }
+
+    Ok(zip_writer.finish()?.into_inner())
+}
+
+/// Fails if `path` is not a file containing `CONTENTS`
+pub fn ensure_extracted(path: &Path) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        fs::read(path).is_ok_and(|ve| ve == CONTENTS),
+        "\"{}\" was not extracted",
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Fails if `path` exists (without following symlinks)
+pub fn ensure_missing(path: &Path) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        fs::symlink_metadata(path).is_err(),
+        "\"{}\" was created",
+        path.display()
+    );
+
+    Ok(())
+}
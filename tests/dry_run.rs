@@ -0,0 +1,42 @@
+//! Runs extractions with "--dry-run", which must not write anything
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+
+fn check_dry_run(format: Format) -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new(format!("dry-run-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(format, &["dir/", "dir/file"])?;
+
+    let output = test_directory.run_tarx(archive_path.as_path(), &["--dry-run"])?;
+
+    common::ensure_missing(test_directory.extracted("").as_path())?;
+
+    anyhow::ensure!(
+        String::from_utf8_lossy(&output.stdout).contains("dir/file"),
+        "Entry was not reported"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_dry_run() -> anyhow::Result<()> {
+    check_dry_run(Format::SevenZ)
+}
+
+#[test]
+fn tar_dry_run() -> anyhow::Result<()> {
+    check_dry_run(Format::Tar)
+}
+
+#[test]
+fn zip_dry_run() -> anyhow::Result<()> {
+    check_dry_run(Format::Zip)
+}
@@ -0,0 +1,52 @@
+//! Extracts archives with "--include" and "--exclude" patterns
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+
+/// Only entries matching an include pattern and no exclude pattern are extracted
+fn check_include_exclude(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("include-exclude-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(
+        format,
+        &[
+            "pkg/include/",
+            "pkg/include/file.h",
+            "pkg/include/debug.pdb",
+            "pkg/src/file.c",
+        ],
+    )?;
+
+    test_directory.run_tarx(
+        archive_path.as_path(),
+        &["--include", "*/include/**", "--exclude", "**/*.pdb"],
+    )?;
+
+    common::ensure_extracted(test_directory.extracted("pkg/include/file.h").as_path())?;
+    common::ensure_missing(test_directory.extracted("pkg/include/debug.pdb").as_path())?;
+    common::ensure_missing(test_directory.extracted("pkg/src").as_path())?;
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_include_exclude() -> anyhow::Result<()> {
+    check_include_exclude(Format::SevenZ)
+}
+
+#[test]
+fn tar_include_exclude() -> anyhow::Result<()> {
+    check_include_exclude(Format::Tar)
+}
+
+#[test]
+fn zip_include_exclude() -> anyhow::Result<()> {
+    check_include_exclude(Format::Zip)
+}
@@ -0,0 +1,41 @@
+//! Extracts archives with "--junk-paths"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+
+/// Files are extracted directly into the new directory, and directories are not created
+fn check_junk_paths(format: Format) -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new(format!("junk-paths-{}", format.extension()).as_str())?;
+
+    let archive_path =
+        test_directory.write_format_archive(format, &["dir/", "dir/first", "dir/sub/second"])?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--junk-paths"])?;
+
+    common::ensure_extracted(test_directory.extracted("first").as_path())?;
+    common::ensure_extracted(test_directory.extracted("second").as_path())?;
+    common::ensure_missing(test_directory.extracted("dir").as_path())?;
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_junk_paths() -> anyhow::Result<()> {
+    check_junk_paths(Format::SevenZ)
+}
+
+#[test]
+fn tar_junk_paths() -> anyhow::Result<()> {
+    check_junk_paths(Format::Tar)
+}
+
+#[test]
+fn zip_junk_paths() -> anyhow::Result<()> {
+    check_junk_paths(Format::Zip)
+}
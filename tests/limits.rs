@@ -0,0 +1,93 @@
+//! Extracts archives that exceed the limits
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory, CONTENTS};
+use std::{fs, path::Path};
+use tar::EntryType;
+
+/// Every entry counts towards "--max-entries", not only regular files
+#[test]
+fn tar_max_entries() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-max-entries")?;
+
+    let mut builder = tar::Builder::new(Vec::new());
+
+    common::append_tar_entry(
+        &mut builder,
+        b"pax_global_header",
+        EntryType::XGlobalHeader,
+        None,
+        b"19 comment=archive\n",
+    )?;
+    common::append_tar_entry(&mut builder, b"file", EntryType::Regular, None, CONTENTS)?;
+    common::append_tar_entry(
+        &mut builder,
+        b"link",
+        EntryType::Symlink,
+        Some(b"file"),
+        &[],
+    )?;
+
+    let archive_path = test_directory.write_archive("archive.tar", &builder.into_inner()?)?;
+
+    let output =
+        test_directory.run_tarx_failing(archive_path.as_path(), &["--max-entries", "2"])?;
+
+    anyhow::ensure!(
+        output.contains("--max-entries"),
+        "Limit was not reported: {output}"
+    );
+
+    common::ensure_missing(test_directory.extracted("").as_path())?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--max-entries", "3"])?;
+
+    anyhow::ensure!(
+        fs::read_link(test_directory.extracted("link"))? == Path::new("file"),
+        "Symlink was not extracted"
+    );
+
+    Ok(())
+}
+
+fn check_max_path_components(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("max-path-components-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(format, &["a/b/file"])?;
+
+    let output =
+        test_directory.run_tarx_failing(archive_path.as_path(), &["--max-path-components", "2"])?;
+
+    anyhow::ensure!(
+        output.contains("--max-path-components"),
+        "Limit was not reported: {output}"
+    );
+
+    test_directory.run_tarx(archive_path.as_path(), &["--max-path-components", "3"])?;
+
+    common::ensure_extracted(test_directory.extracted("a/b/file").as_path())?;
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_max_path_components() -> anyhow::Result<()> {
+    check_max_path_components(Format::SevenZ)
+}
+
+#[test]
+fn tar_max_path_components() -> anyhow::Result<()> {
+    check_max_path_components(Format::Tar)
+}
+
+#[test]
+fn zip_max_path_components() -> anyhow::Result<()> {
+    check_max_path_components(Format::Zip)
+}
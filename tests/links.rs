@@ -0,0 +1,235 @@
+//! Extracts hard links, and the links and file copies stored in RAR archives. Link targets that resolve to locations
+//! outside of the new directory are rejected.
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{TestDirectory, CONTENTS};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tar::EntryType;
+
+/// Regular files contain `CONTENTS`, and links point to the given targets
+fn build_tar(entries: &[(&str, EntryType, Option<&str>)]) -> anyhow::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+
+    for &(st, entry_type, op) in entries {
+        let data = if entry_type == EntryType::Regular {
+            CONTENTS
+        } else {
+            &[]
+        };
+
+        common::append_tar_entry(
+            &mut builder,
+            st.as_bytes(),
+            entry_type,
+            op.map(str::as_bytes),
+            data,
+        )?;
+    }
+
+    Ok(builder.into_inner()?)
+}
+
+/// Fails if `path` is not a hard link to "dir/file" in the new directory
+fn ensure_hard_link(test_directory: &TestDirectory, path: &Path) -> anyhow::Result<()> {
+    common::ensure_extracted(path)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        anyhow::ensure!(
+            fs::metadata(path)?.ino() == fs::metadata(test_directory.extracted("dir/file"))?.ino(),
+            "\"{}\" is not a hard link",
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Writes the archive next to "outside/secret", which must not be linked into the new directory
+fn write_escape_archive(
+    test_directory: &TestDirectory,
+    file_name: &str,
+    bytes: &[u8],
+) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(test_directory.path.join("outside"))?;
+    fs::write(test_directory.path.join("outside").join("secret"), CONTENTS)?;
+
+    test_directory.write_archive(file_name, bytes)
+}
+
+/// The hard link "escape" points to "../outside/secret", and is skipped like entries with ".." components
+fn check_hard_link_escape(
+    test_directory: &TestDirectory,
+    file_name: &str,
+    bytes: &[u8],
+) -> anyhow::Result<()> {
+    let archive_path = write_escape_archive(test_directory, file_name, bytes)?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    common::ensure_missing(test_directory.extracted("escape").as_path())
+}
+
+/// The symlink "link" points to "../outside", and the hard link "escape" to "link/secret". Resolving the target through
+/// the symlink is an error.
+fn check_hard_link_symlink_escape(
+    test_directory: &TestDirectory,
+    file_name: &str,
+    bytes: &[u8],
+) -> anyhow::Result<()> {
+    let archive_path = write_escape_archive(test_directory, file_name, bytes)?;
+
+    let output = test_directory.run_tarx_failing(archive_path.as_path(), &[])?;
+
+    anyhow::ensure!(
+        output.contains("outside of the new directory"),
+        "Unexpected error: {output}"
+    );
+
+    common::ensure_missing(test_directory.extracted("").as_path())
+}
+
+#[test]
+fn tar_hard_link() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-hard-link")?;
+
+    let archive_path = test_directory.write_archive(
+        "archive.tar",
+        &build_tar(&[
+            ("dir/file", EntryType::Regular, None),
+            ("hard_link", EntryType::Link, Some("dir/file")),
+        ])?,
+    )?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    ensure_hard_link(
+        &test_directory,
+        test_directory.extracted("hard_link").as_path(),
+    )
+}
+
+#[test]
+fn tar_hard_link_escape() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-hard-link-escape")?;
+
+    check_hard_link_escape(
+        &test_directory,
+        "archive.tar",
+        &build_tar(&[("escape", EntryType::Link, Some("../outside/secret"))])?,
+    )
+}
+
+#[test]
+fn tar_hard_link_symlink_escape() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-hard-link-symlink-escape")?;
+
+    check_hard_link_symlink_escape(
+        &test_directory,
+        "archive.tar",
+        &build_tar(&[
+            ("link", EntryType::Symlink, Some("../outside")),
+            ("escape", EntryType::Link, Some("link/secret")),
+        ])?,
+    )
+}
+
+/// The zip crate cannot write PKWARE's Unix extra field, which stores the target of the hard link
+#[test]
+fn zip_hard_link() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("zip-hard-link")?;
+
+    let archive_path =
+        test_directory.write_archive("archive.zip", &common::read_fixture("hard_link.zip")?)?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    ensure_hard_link(
+        &test_directory,
+        test_directory.extracted("hard_link").as_path(),
+    )
+}
+
+#[test]
+fn zip_hard_link_escape() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("zip-hard-link-escape")?;
+
+    check_hard_link_escape(
+        &test_directory,
+        "archive.zip",
+        &common::read_fixture("hard_link_escape.zip")?,
+    )
+}
+
+#[test]
+fn zip_hard_link_symlink_escape() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("zip-hard-link-symlink-escape")?;
+
+    check_hard_link_symlink_escape(
+        &test_directory,
+        "archive.zip",
+        &common::read_fixture("hard_link_symlink_escape.zip")?,
+    )
+}
+
+/// Contains "dir/file", and a hard link, a file copy ("copy"), and a symlink ("symlink") that all refer to it
+#[cfg(feature = "foreign")]
+#[test]
+fn rar_links() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("rar-links")?;
+
+    let archive_path =
+        test_directory.write_archive("archive.rar", &common::read_fixture("links.rar")?)?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    ensure_hard_link(
+        &test_directory,
+        test_directory.extracted("hard_link").as_path(),
+    )?;
+
+    // A copy is a separate file
+    common::ensure_extracted(test_directory.extracted("copy").as_path())?;
+
+    anyhow::ensure!(
+        fs::read_link(test_directory.extracted("symlink"))? == Path::new("dir/file"),
+        "Symlink was not extracted as a symlink"
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "foreign")]
+#[test]
+fn rar_hard_link_escape() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("rar-hard-link-escape")?;
+
+    check_hard_link_escape(
+        &test_directory,
+        "archive.rar",
+        &common::read_fixture("hard_link_escape.rar")?,
+    )
+}
+
+#[cfg(feature = "foreign")]
+#[test]
+fn rar_hard_link_symlink_escape() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("rar-hard-link-symlink-escape")?;
+
+    check_hard_link_symlink_escape(
+        &test_directory,
+        "archive.rar",
+        &common::read_fixture("hard_link_symlink_escape.rar")?,
+    )
+}
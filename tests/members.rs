@@ -0,0 +1,76 @@
+//! Extracts the members named on the command line
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+
+/// A directory selects everything in it
+fn check_members(format: Format) -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new(format!("members-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(
+        format,
+        &["dir/", "dir/file", "dir/sub/file", "file", "other"],
+    )?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["dir", "file"])?;
+
+    common::ensure_extracted(test_directory.extracted("dir/file").as_path())?;
+    common::ensure_extracted(test_directory.extracted("dir/sub/file").as_path())?;
+    common::ensure_extracted(test_directory.extracted("file").as_path())?;
+    common::ensure_missing(test_directory.extracted("other").as_path())?;
+
+    Ok(())
+}
+
+/// Members that are not in the archive are an error
+fn check_missing_member(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("missing-member-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(format, &["file"])?;
+
+    let output = test_directory.run_tarx_failing(archive_path.as_path(), &["file", "missing"])?;
+
+    anyhow::ensure!(
+        output.contains("missing"),
+        "Missing member was not reported: {output}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_members() -> anyhow::Result<()> {
+    check_members(Format::SevenZ)
+}
+
+#[test]
+fn seven_z_missing_member() -> anyhow::Result<()> {
+    check_missing_member(Format::SevenZ)
+}
+
+#[test]
+fn tar_members() -> anyhow::Result<()> {
+    check_members(Format::Tar)
+}
+
+#[test]
+fn tar_missing_member() -> anyhow::Result<()> {
+    check_missing_member(Format::Tar)
+}
+
+#[test]
+fn zip_members() -> anyhow::Result<()> {
+    check_members(Format::Zip)
+}
+
+#[test]
+fn zip_missing_member() -> anyhow::Result<()> {
+    check_missing_member(Format::Zip)
+}
@@ -0,0 +1,171 @@
+//! Extracts archives when the new directory already exists, and with "--dir-name"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+use std::fs;
+
+/// An existing new directory is an error, unless "--unique-dir" or "--merge" is used
+fn check_existing_new_directory(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("existing-new-directory-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(format, &["file"])?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    let output = test_directory.run_tarx_failing(archive_path.as_path(), &[])?;
+
+    anyhow::ensure!(
+        output.contains("already exists"),
+        "Existing directory was not reported: {output}"
+    );
+
+    test_directory.run_tarx(archive_path.as_path(), &["--unique-dir"])?;
+
+    common::ensure_extracted(test_directory.path.join("archive (1)/file").as_path())?;
+
+    fs::remove_file(test_directory.extracted("file"))?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--merge"])?;
+
+    common::ensure_extracted(test_directory.extracted("file").as_path())?;
+
+    Ok(())
+}
+
+/// Entries are extracted into a hidden staging directory, so a failed extraction leaves nothing next to the archive
+fn check_failed_staging(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("failed-staging-{}", format.extension()).as_str())?;
+
+    // "file/sub" fails, since "file" is not a directory
+    let archive_path =
+        test_directory.write_format_archive(format, &["dir/", "file", "file/sub"])?;
+
+    test_directory.run_tarx_failing(archive_path.as_path(), &[])?;
+
+    let file_names = fs::read_dir(test_directory.path.as_path())?
+        .map(|re| Ok(re?.file_name()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    anyhow::ensure!(
+        file_names == [format!("archive.{}", format.extension()).as_str()],
+        "Directory containing the archive contains other entries: {file_names:?}"
+    );
+
+    Ok(())
+}
+
+/// When extraction fails, files replaced with "--merge --overwrite" are restored, and the entries that were created are
+/// removed
+fn check_failed_merge_overwrite(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("failed-merge-overwrite-{}", format.extension()).as_str())?;
+
+    // "file/sub" fails, since "file" is not a directory
+    let archive_path = test_directory.write_format_archive(format, &["new", "file", "file/sub"])?;
+
+    fs::create_dir_all(test_directory.extracted("").as_path())?;
+    fs::write(test_directory.extracted("file"), b"existing\n")?;
+
+    test_directory.run_tarx_failing(archive_path.as_path(), &["--merge", "--overwrite"])?;
+
+    anyhow::ensure!(
+        fs::read(test_directory.extracted("file"))? == b"existing\n",
+        "Replaced file was not restored"
+    );
+
+    let file_names = fs::read_dir(test_directory.extracted("").as_path())?
+        .map(|re| Ok(re?.file_name()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    anyhow::ensure!(
+        file_names == ["file"],
+        "New directory contains other entries: {file_names:?}"
+    );
+
+    Ok(())
+}
+
+fn check_dir_name(format: Format) -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new(format!("dir-name-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(format, &["file"])?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--dir-name", "{stem}-extracted"])?;
+
+    common::ensure_extracted(test_directory.path.join("archive-extracted/file").as_path())?;
+
+    // The name must be a single component
+    test_directory.run_tarx_failing(archive_path.as_path(), &["--dir-name", "../{stem}"])?;
+
+    common::ensure_missing(test_directory.path.join("../archive").as_path())?;
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_dir_name() -> anyhow::Result<()> {
+    check_dir_name(Format::SevenZ)
+}
+
+#[test]
+fn seven_z_existing_new_directory() -> anyhow::Result<()> {
+    check_existing_new_directory(Format::SevenZ)
+}
+
+#[test]
+fn seven_z_failed_merge_overwrite() -> anyhow::Result<()> {
+    check_failed_merge_overwrite(Format::SevenZ)
+}
+
+#[test]
+fn seven_z_failed_staging() -> anyhow::Result<()> {
+    check_failed_staging(Format::SevenZ)
+}
+
+#[test]
+fn tar_dir_name() -> anyhow::Result<()> {
+    check_dir_name(Format::Tar)
+}
+
+#[test]
+fn tar_existing_new_directory() -> anyhow::Result<()> {
+    check_existing_new_directory(Format::Tar)
+}
+
+#[test]
+fn tar_failed_merge_overwrite() -> anyhow::Result<()> {
+    check_failed_merge_overwrite(Format::Tar)
+}
+
+#[test]
+fn tar_failed_staging() -> anyhow::Result<()> {
+    check_failed_staging(Format::Tar)
+}
+
+#[test]
+fn zip_dir_name() -> anyhow::Result<()> {
+    check_dir_name(Format::Zip)
+}
+
+#[test]
+fn zip_existing_new_directory() -> anyhow::Result<()> {
+    check_existing_new_directory(Format::Zip)
+}
+
+#[test]
+fn zip_failed_merge_overwrite() -> anyhow::Result<()> {
+    check_failed_merge_overwrite(Format::Zip)
+}
+
+#[test]
+fn zip_failed_staging() -> anyhow::Result<()> {
+    check_failed_staging(Format::Zip)
+}
@@ -0,0 +1,111 @@
+//! Extracts archives containing the same path twice with each overwrite policy
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+use std::fs;
+
+const FIRST: &[u8] = b"first\n";
+const SECOND: &[u8] = b"second\n";
+
+/// The second entry finds the file written by the first one
+fn check_overwrite_policy(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("overwrite-policy-{}", format.extension()).as_str())?;
+
+    // Zip archives cannot contain the same name twice
+    let second_path = match format {
+        Format::Zip => "./file",
+        Format::SevenZ | Format::Tar => "file",
+    };
+
+    let bytes = format.build_files(&[("file", FIRST), (second_path, SECOND)])?;
+
+    // Existing files are an error by default
+    let output = test_directory.run_tarx_failing(
+        test_directory
+            .write_archive(format!("error.{}", format.extension()).as_str(), &bytes)?
+            .as_path(),
+        &[],
+    )?;
+
+    anyhow::ensure!(
+        output.contains("already exists"),
+        "Existing file was not reported: {output}"
+    );
+
+    // The new directory is named after the archive
+    for (arg, name, file_contents) in [
+        ("--skip-existing", "skip-existing", FIRST),
+        ("--overwrite", "overwrite", SECOND),
+        ("--rename", "rename", FIRST),
+    ] {
+        let archive_path = test_directory
+            .write_archive(format!("{name}.{}", format.extension()).as_str(), &bytes)?;
+
+        test_directory.run_tarx(archive_path.as_path(), &[arg])?;
+
+        anyhow::ensure!(
+            fs::read(test_directory.path.join(name).join("file"))? == file_contents,
+            "Wrong entry was kept with {arg}"
+        );
+    }
+
+    anyhow::ensure!(
+        fs::read(test_directory.path.join("rename/file (1)"))? == SECOND,
+        "Second entry was not renamed"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_overwrite_policy() -> anyhow::Result<()> {
+    check_overwrite_policy(Format::SevenZ)
+}
+
+#[test]
+fn tar_overwrite_policy() -> anyhow::Result<()> {
+    check_overwrite_policy(Format::Tar)
+}
+
+#[test]
+fn zip_overwrite_policy() -> anyhow::Result<()> {
+    check_overwrite_policy(Format::Zip)
+}
+
+/// Names that are not valid UTF-8 are kept as they are
+#[cfg(unix)]
+#[test]
+fn tar_rename_non_utf_8() -> anyhow::Result<()> {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+    use tar::EntryType;
+
+    let test_directory = TestDirectory::new("tar-rename-non-utf-8")?;
+
+    let mut builder = tar::Builder::new(Vec::new());
+
+    for sl in [FIRST, SECOND] {
+        common::append_tar_entry(&mut builder, b"caf\xe9.txt", EntryType::Regular, None, sl)?;
+    }
+
+    let archive_path = test_directory.write_archive("archive.tar", &builder.into_inner()?)?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--rename"])?;
+
+    anyhow::ensure!(
+        fs::read(
+            test_directory
+                .extracted("")
+                .join(OsStr::from_bytes(b"caf\xe9 (1).txt"))
+        )? == SECOND,
+        "Second entry was not renamed"
+    );
+
+    Ok(())
+}
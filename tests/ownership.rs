@@ -0,0 +1,81 @@
+//! Extracts entries with "--same-owner", "--owner-map", and "--group"
+
+#![cfg(unix)]
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{TestDirectory, CONTENTS};
+use std::{fs, os::unix::fs::MetadataExt};
+use tar::{EntryType, Header};
+
+/// Only checked when running as root, since other users cannot change the owner of files
+#[test]
+fn tar_ownership() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-ownership")?;
+
+    let mut builder = tar::Builder::new(Vec::new());
+
+    for (path, uid) in [("mapped", 1_000_u64), ("kept", 2_000_u64)] {
+        let mut header = Header::new_ustar();
+
+        header.set_path(path)?;
+        header.set_entry_type(EntryType::Regular);
+        header.set_mode(0o644);
+        header.set_uid(uid);
+        header.set_gid(uid);
+        header.set_size(u64::try_from(CONTENTS.len())?);
+        header.set_cksum();
+
+        builder.append(&header, CONTENTS)?;
+    }
+
+    let archive_path = test_directory.write_archive("archive.tar", &builder.into_inner()?)?;
+
+    if fs::metadata(archive_path.as_path())?.uid() != 0_u32 {
+        return Ok(());
+    }
+
+    let owner_map_path = test_directory.path.join("owner-map");
+
+    fs::write(owner_map_path.as_path(), "1000 3000\n")?;
+
+    let output = test_directory.run_tarx(
+        archive_path.as_path(),
+        &[
+            "--same-owner",
+            "--owner-map",
+            &owner_map_path.to_string_lossy(),
+            "--group",
+            "4000",
+        ],
+    )?;
+
+    let mapped_metadata = fs::symlink_metadata(test_directory.extracted("mapped"))?;
+    let kept_metadata = fs::symlink_metadata(test_directory.extracted("kept"))?;
+
+    anyhow::ensure!(
+        mapped_metadata.uid() == 3_000_u32 && mapped_metadata.gid() == 4_000_u32,
+        "Owner is {}:{}",
+        mapped_metadata.uid(),
+        mapped_metadata.gid()
+    );
+
+    anyhow::ensure!(
+        kept_metadata.uid() == 2_000_u32 && kept_metadata.gid() == 4_000_u32,
+        "Owner is {}:{}",
+        kept_metadata.uid(),
+        kept_metadata.gid()
+    );
+
+    // IDs that are not in the map are reported
+    anyhow::ensure!(
+        String::from_utf8_lossy(&output.stderr).contains("2000"),
+        "Unmapped ID was not reported"
+    );
+
+    Ok(())
+}
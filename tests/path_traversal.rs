@@ -0,0 +1,307 @@
+//! Extracts crafted archives with entries that try to write outside of the new directory, and checks that nothing is
+//! written there
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{TestDirectory, CONTENTS};
+use flate2::{write::GzEncoder, Compression};
+use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+use std::{
+    fs,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+};
+use tar::EntryType;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// Creates a test directory containing a directory named "outside", which must stay empty
+fn new_test_directory(name: &str) -> anyhow::Result<TestDirectory> {
+    let test_directory = TestDirectory::new(name)?;
+
+    fs::create_dir_all(outside(&test_directory))?;
+
+    Ok(test_directory)
+}
+
+fn outside(test_directory: &TestDirectory) -> PathBuf {
+    test_directory.path.join("outside")
+}
+
+/// Fails if anything was written to "outside", or next to the new directory
+fn ensure_nothing_outside(
+    test_directory: &TestDirectory,
+    new_directory_name: &str,
+) -> anyhow::Result<()> {
+    let outside_entries = fs::read_dir(outside(test_directory))?.count();
+
+    anyhow::ensure!(
+        outside_entries == 0_usize,
+        "{outside_entries} entries were written to \"outside\""
+    );
+
+    for re in fs::read_dir(test_directory.path.as_path())? {
+        let file_name = re?.file_name();
+
+        anyhow::ensure!(
+            file_name == "outside"
+                || file_name == new_directory_name
+                || file_name.to_string_lossy().starts_with(new_directory_name),
+            "{file_name:?} was written next to the new directory"
+        );
+    }
+
+    Ok(())
+}
+
+/// Entries with absolute paths and paths containing ".." components
+fn build_traversal_tar() -> anyhow::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+
+    common::append_tar_entry(&mut builder, b"safe", EntryType::Regular, None, CONTENTS)?;
+    common::append_tar_entry(&mut builder, b"../evil", EntryType::Regular, None, CONTENTS)?;
+    common::append_tar_entry(
+        &mut builder,
+        b"dir/../../evil",
+        EntryType::Regular,
+        None,
+        CONTENTS,
+    )?;
+    common::append_tar_entry(&mut builder, b"/abs", EntryType::Regular, None, CONTENTS)?;
+
+    Ok(builder.into_inner()?)
+}
+
+/// A symlink pointing outside of the new directory, followed by entries written through it
+fn build_symlink_tar(link_target: &Path, entry_path: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+
+    common::append_tar_entry(
+        &mut builder,
+        b"link",
+        EntryType::Symlink,
+        Some(link_target.as_os_str().as_encoded_bytes()),
+        &[],
+    )?;
+    common::append_tar_entry(&mut builder, entry_path, EntryType::Regular, None, CONTENTS)?;
+
+    Ok(builder.into_inner()?)
+}
+
+fn gzip(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut gz_encoder = GzEncoder::new(Vec::new(), Compression::default());
+
+    gz_encoder.write_all(bytes)?;
+
+    Ok(gz_encoder.finish()?)
+}
+
+/// Checks that the safe entry was extracted, and that the others were skipped
+fn check_traversal(test_directory: &TestDirectory, archive_path: &Path) -> anyhow::Result<()> {
+    let output = test_directory.run_tarx_unchecked(archive_path, &[])?;
+
+    anyhow::ensure!(
+        output.status.success(),
+        "Extraction failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    anyhow::ensure!(
+        fs::read(test_directory.path.join("archive").join("safe"))? == CONTENTS,
+        "Safe entry was not extracted"
+    );
+
+    ensure_nothing_outside(test_directory, "archive")
+}
+
+/// Checks that extraction failed, and that nothing was written through the symlink
+fn check_symlink_escape(test_directory: &TestDirectory, archive_path: &Path) -> anyhow::Result<()> {
+    let output = test_directory.run_tarx_unchecked(archive_path, &[])?;
+
+    anyhow::ensure!(
+        !output.status.success(),
+        "Extraction through a symlink pointing outside of the new directory succeeded"
+    );
+
+    anyhow::ensure!(
+        String::from_utf8_lossy(&output.stderr).contains("outside of the new directory"),
+        "Unexpected error: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    ensure_nothing_outside(test_directory, "archive")
+}
+
+#[test]
+fn tar_traversal() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("tar-traversal")?;
+
+    let archive_path = test_directory.write_archive("archive.tar", &build_traversal_tar()?)?;
+
+    check_traversal(&test_directory, archive_path.as_path())
+}
+
+#[test]
+fn tar_gz_traversal() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("tar-gz-traversal")?;
+
+    let archive_path =
+        test_directory.write_archive("archive.tar.gz", &gzip(&build_traversal_tar()?)?)?;
+
+    check_traversal(&test_directory, archive_path.as_path())
+}
+
+#[test]
+fn tar_symlink_escape() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("tar-symlink-escape")?;
+
+    let archive_path = test_directory.write_archive(
+        "archive.tar",
+        &build_symlink_tar(outside(&test_directory).as_path(), b"link/pwned")?,
+    )?;
+
+    check_symlink_escape(&test_directory, archive_path.as_path())
+}
+
+#[test]
+fn tar_relative_symlink_escape() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("tar-relative-symlink-escape")?;
+
+    // Creating "sub" would create "outside/sub"
+    let archive_path = test_directory.write_archive(
+        "archive.tar",
+        &build_symlink_tar(Path::new("../outside"), b"link/sub/pwned")?,
+    )?;
+
+    check_symlink_escape(&test_directory, archive_path.as_path())
+}
+
+#[test]
+fn tar_gz_symlink_escape() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("tar-gz-symlink-escape")?;
+
+    let archive_path = test_directory.write_archive(
+        "archive.tar.gz",
+        &gzip(&build_symlink_tar(
+            outside(&test_directory).as_path(),
+            b"link/pwned",
+        )?)?,
+    )?;
+
+    check_symlink_escape(&test_directory, archive_path.as_path())
+}
+
+#[test]
+fn zip_traversal() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("zip-traversal")?;
+
+    let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    let simple_file_options =
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    for st in ["safe", "../evil", "dir/../../evil", "/abs"] {
+        zip_writer.start_file(st, simple_file_options)?;
+        zip_writer.write_all(CONTENTS)?;
+    }
+
+    let archive_path =
+        test_directory.write_archive("archive.zip", &zip_writer.finish()?.into_inner())?;
+
+    check_traversal(&test_directory, archive_path.as_path())
+}
+
+#[test]
+fn zip_symlink_escape() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("zip-symlink-escape")?;
+
+    let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    let simple_file_options =
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    zip_writer.add_symlink(
+        "link",
+        outside(&test_directory).to_string_lossy(),
+        simple_file_options,
+    )?;
+    zip_writer.start_file("link/pwned", simple_file_options)?;
+    zip_writer.write_all(CONTENTS)?;
+
+    let archive_path =
+        test_directory.write_archive("archive.zip", &zip_writer.finish()?.into_inner())?;
+
+    check_symlink_escape(&test_directory, archive_path.as_path())
+}
+
+#[test]
+fn seven_z_traversal() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("seven-z-traversal")?;
+
+    let mut seven_z_writer = SevenZWriter::new(Cursor::new(Vec::new()))?;
+
+    for st in ["safe", "../evil", "dir/../../evil", "/abs"] {
+        let mut seven_z_archive_entry = SevenZArchiveEntry::new();
+
+        seven_z_archive_entry.name = st.to_owned();
+
+        seven_z_writer.push_archive_entry(seven_z_archive_entry, Some(CONTENTS))?;
+    }
+
+    let archive_path =
+        test_directory.write_archive("archive.7z", &seven_z_writer.finish()?.into_inner())?;
+
+    check_traversal(&test_directory, archive_path.as_path())
+}
+
+#[test]
+fn seven_z_symlink_escape() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("seven-z-symlink-escape")?;
+
+    let mut seven_z_writer = SevenZWriter::new(Cursor::new(Vec::new()))?;
+
+    common::push_seven_z_symlink(
+        &mut seven_z_writer,
+        "link",
+        outside(&test_directory).to_string_lossy().as_ref(),
+    )?;
+
+    let mut seven_z_archive_entry = SevenZArchiveEntry::new();
+
+    "link/pwned".clone_into(&mut seven_z_archive_entry.name);
+
+    seven_z_writer.push_archive_entry(seven_z_archive_entry, Some(CONTENTS))?;
+
+    let archive_path =
+        test_directory.write_archive("archive.7z", &seven_z_writer.finish()?.into_inner())?;
+
+    check_symlink_escape(&test_directory, archive_path.as_path())
+}
+
+/// Contains the same entries as the tar archive built by `build_traversal_tar`
+#[cfg(feature = "foreign")]
+#[test]
+fn rar_traversal() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("rar-traversal")?;
+
+    let archive_path =
+        test_directory.write_archive("archive.rar", &common::read_fixture("traversal.rar")?)?;
+
+    check_traversal(&test_directory, archive_path.as_path())
+}
+
+/// Contains a symlink "link" pointing to "../outside", followed by "link/sub/pwned"
+#[cfg(feature = "foreign")]
+#[test]
+fn rar_symlink_escape() -> anyhow::Result<()> {
+    let test_directory = new_test_directory("rar-symlink-escape")?;
+
+    let archive_path = test_directory
+        .write_archive("archive.rar", &common::read_fixture("symlink_escape.rar")?)?;
+
+    check_symlink_escape(&test_directory, archive_path.as_path())
+}
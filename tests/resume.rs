@@ -0,0 +1,89 @@
+//! Continues failed extractions with "--resume"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory, CONTENTS};
+use std::fs;
+use tar::EntryType;
+
+/// An extraction that failed with "--keep-partial" is continued by "--resume", without extracting the entries that
+/// were already extracted again
+fn check_resume(format: Format) -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new(format!("resume-{}", format.extension()).as_str())?;
+
+    let archive_path =
+        test_directory.write_format_archive(format, &["first", "second", "third"])?;
+
+    // The existing file makes the extraction fail at the last entry
+    fs::create_dir_all(test_directory.extracted(""))?;
+    fs::write(test_directory.extracted("third"), b"existing\n")?;
+
+    test_directory.run_tarx_failing(archive_path.as_path(), &["--merge", "--keep-partial"])?;
+
+    common::ensure_extracted(test_directory.extracted("first").as_path())?;
+    common::ensure_extracted(test_directory.extracted("second").as_path())?;
+
+    fs::remove_file(test_directory.extracted("third"))?;
+
+    let output = test_directory.run_tarx(archive_path.as_path(), &["--merge", "--resume"])?;
+
+    anyhow::ensure!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("already extracted by the extraction being resumed"),
+        "Entries were extracted again"
+    );
+
+    for st in ["first", "second", "third"] {
+        common::ensure_extracted(test_directory.extracted(st).as_path())?;
+    }
+
+    common::ensure_missing(test_directory.extracted(".tarx-journal").as_path())?;
+
+    Ok(())
+}
+
+#[test]
+fn tar_resume() -> anyhow::Result<()> {
+    check_resume(Format::Tar)
+}
+
+#[test]
+fn zip_resume() -> anyhow::Result<()> {
+    check_resume(Format::Zip)
+}
+
+/// An entry with the name of the journal must not overwrite it
+#[test]
+fn tar_journal_name() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-journal-name")?;
+
+    let mut builder = tar::Builder::new(Vec::new());
+
+    common::append_tar_entry(
+        &mut builder,
+        b".tarx-journal",
+        EntryType::Regular,
+        None,
+        CONTENTS,
+    )?;
+    common::append_tar_entry(&mut builder, b"file", EntryType::Regular, None, CONTENTS)?;
+
+    let archive_path = test_directory.write_archive("archive.tar", &builder.into_inner()?)?;
+
+    let output = test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    common::ensure_extracted(test_directory.extracted("file").as_path())?;
+    common::ensure_missing(test_directory.extracted(".tarx-journal").as_path())?;
+
+    anyhow::ensure!(
+        String::from_utf8_lossy(&output.stderr).contains("reserved for the journal"),
+        "Skipped entry was not reported"
+    );
+
+    Ok(())
+}
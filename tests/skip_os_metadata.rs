@@ -0,0 +1,59 @@
+//! Extracts archives with "--skip-os-metadata"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+
+fn check_skip_os_metadata(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("skip-os-metadata-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(
+        format,
+        &[
+            "__MACOSX/",
+            "__MACOSX/._file",
+            "._file",
+            ".DS_Store",
+            "dir/Thumbs.db",
+            "dir/desktop.ini",
+            "file",
+        ],
+    )?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--skip-os-metadata"])?;
+
+    common::ensure_extracted(test_directory.extracted("file").as_path())?;
+
+    for st in [
+        "__MACOSX",
+        "._file",
+        ".DS_Store",
+        "dir/Thumbs.db",
+        "dir/desktop.ini",
+    ] {
+        common::ensure_missing(test_directory.extracted(st).as_path())?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_skip_os_metadata() -> anyhow::Result<()> {
+    check_skip_os_metadata(Format::SevenZ)
+}
+
+#[test]
+fn tar_skip_os_metadata() -> anyhow::Result<()> {
+    check_skip_os_metadata(Format::Tar)
+}
+
+#[test]
+fn zip_skip_os_metadata() -> anyhow::Result<()> {
+    check_skip_os_metadata(Format::Zip)
+}
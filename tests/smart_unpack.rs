@@ -0,0 +1,50 @@
+//! Extracts archives with and without a single top-level directory using "--smart-unpack"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+
+/// The top-level directory is not nested in the new directory, unless there is more than one top-level entry
+fn check_smart_unpack(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("smart-unpack-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(format, &["root/", "root/file"])?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--smart-unpack"])?;
+
+    common::ensure_extracted(test_directory.extracted("file").as_path())?;
+    common::ensure_missing(test_directory.extracted("root").as_path())?;
+
+    let other_archive_path = test_directory.write_archive(
+        format!("other.{}", format.extension()).as_str(),
+        &format.build(&["root/file", "file"])?,
+    )?;
+
+    test_directory.run_tarx(other_archive_path.as_path(), &["--smart-unpack"])?;
+
+    common::ensure_extracted(test_directory.path.join("other/root/file").as_path())?;
+    common::ensure_extracted(test_directory.path.join("other/file").as_path())?;
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_smart_unpack() -> anyhow::Result<()> {
+    check_smart_unpack(Format::SevenZ)
+}
+
+#[test]
+fn tar_smart_unpack() -> anyhow::Result<()> {
+    check_smart_unpack(Format::Tar)
+}
+
+#[test]
+fn zip_smart_unpack() -> anyhow::Result<()> {
+    check_smart_unpack(Format::Zip)
+}
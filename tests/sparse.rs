@@ -0,0 +1,50 @@
+//! Extracts tar archives with "--sparse"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{TestDirectory, CONTENTS};
+use std::{fs, path::Path};
+use tar::EntryType;
+
+/// "--sparse" only changes how regular files are written
+#[test]
+fn tar_sparse_symlink() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-sparse-symlink")?;
+
+    let mut data = CONTENTS.to_vec();
+
+    data.resize(16_384, 0_u8);
+    data.extend_from_slice(CONTENTS);
+
+    let mut builder = tar::Builder::new(Vec::new());
+
+    common::append_tar_entry(&mut builder, b"file", EntryType::Regular, None, &data)?;
+    common::append_tar_entry(
+        &mut builder,
+        b"link",
+        EntryType::Symlink,
+        Some(b"file"),
+        &[],
+    )?;
+
+    let archive_path = test_directory.write_archive("archive.tar", &builder.into_inner()?)?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--sparse"])?;
+
+    anyhow::ensure!(
+        fs::read(test_directory.extracted("file"))? == data,
+        "File was not extracted correctly"
+    );
+
+    anyhow::ensure!(
+        fs::read_link(test_directory.extracted("link"))? == Path::new("file"),
+        "Symlink was not extracted as a symlink"
+    );
+
+    Ok(())
+}
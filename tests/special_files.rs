@@ -0,0 +1,68 @@
+//! Extracts entries with setuid, setgid, or sticky bits, and FIFOs
+
+#![cfg(unix)]
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{TestDirectory, CONTENTS};
+use std::{
+    fs,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+};
+use tar::{EntryType, Header};
+
+/// The special bits are removed and FIFOs are skipped, unless "--keep-special-bits" and "--special-files" are used
+#[test]
+fn tar_special_bits_and_files() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-special-bits-and-files")?;
+
+    let mut builder = tar::Builder::new(Vec::new());
+
+    let mut header = Header::new_ustar();
+
+    header.set_path("setuid")?;
+    header.set_entry_type(EntryType::Regular);
+    header.set_mode(0o4755);
+    header.set_size(u64::try_from(CONTENTS.len())?);
+    header.set_cksum();
+
+    builder.append(&header, CONTENTS)?;
+
+    common::append_tar_entry(&mut builder, b"fifo", EntryType::Fifo, None, &[])?;
+
+    let archive_path = test_directory.write_archive("archive.tar", &builder.into_inner()?)?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    let mode = fs::metadata(test_directory.extracted("setuid"))?
+        .permissions()
+        .mode();
+
+    anyhow::ensure!(mode & 0o7777 == 0o755, "Mode is {mode:o}");
+
+    common::ensure_missing(test_directory.extracted("fifo").as_path())?;
+
+    test_directory.run_tarx(
+        archive_path.as_path(),
+        &["--unique-dir", "--keep-special-bits", "--special-files"],
+    )?;
+
+    let kept_mode = fs::metadata(test_directory.path.join("archive (1)/setuid"))?
+        .permissions()
+        .mode();
+
+    anyhow::ensure!(kept_mode & 0o7777 == 0o4755, "Mode is {kept_mode:o}");
+
+    anyhow::ensure!(
+        fs::symlink_metadata(test_directory.path.join("archive (1)/fifo"))?
+            .file_type()
+            .is_fifo(),
+        "FIFO was not created"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Extracts archives with "--strip-components"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+
+fn check_strip_components(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("strip-components-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(
+        format,
+        &["pkg/", "pkg/src/", "pkg/src/file", "pkg/file", "top"],
+    )?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--strip-components", "1"])?;
+
+    common::ensure_extracted(test_directory.extracted("src/file").as_path())?;
+    common::ensure_extracted(test_directory.extracted("file").as_path())?;
+
+    // Entries with no more components than are stripped
+    common::ensure_missing(test_directory.extracted("pkg").as_path())?;
+    common::ensure_missing(test_directory.extracted("top").as_path())?;
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_strip_components() -> anyhow::Result<()> {
+    check_strip_components(Format::SevenZ)
+}
+
+#[test]
+fn tar_strip_components() -> anyhow::Result<()> {
+    check_strip_components(Format::Tar)
+}
+
+#[test]
+fn zip_strip_components() -> anyhow::Result<()> {
+    check_strip_components(Format::Zip)
+}
@@ -0,0 +1,70 @@
+//! Extracts symlinks stored in 7z and zip archives
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{TestDirectory, CONTENTS};
+use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+use std::{
+    fs,
+    io::{Cursor, Write},
+    path::Path,
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// Fails if "link" is not a symlink to "dir/file"
+fn ensure_symlink_extracted(test_directory: &TestDirectory) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        fs::read_link(test_directory.extracted("link"))? == Path::new("dir/file"),
+        "Symlink was not extracted as a symlink"
+    );
+
+    common::ensure_extracted(test_directory.extracted("link").as_path())
+}
+
+#[test]
+fn seven_z_symlink() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("seven-z-symlink")?;
+
+    let mut seven_z_writer = SevenZWriter::new(Cursor::new(Vec::new()))?;
+
+    let mut seven_z_archive_entry = SevenZArchiveEntry::new();
+
+    "dir/file".clone_into(&mut seven_z_archive_entry.name);
+
+    seven_z_writer.push_archive_entry(seven_z_archive_entry, Some(CONTENTS))?;
+
+    common::push_seven_z_symlink(&mut seven_z_writer, "link", "dir/file")?;
+
+    let archive_path =
+        test_directory.write_archive("archive.7z", &seven_z_writer.finish()?.into_inner())?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    ensure_symlink_extracted(&test_directory)
+}
+
+#[test]
+fn zip_symlink() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("zip-symlink")?;
+
+    let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    let simple_file_options =
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    zip_writer.start_file("dir/file", simple_file_options)?;
+    zip_writer.write_all(CONTENTS)?;
+    zip_writer.add_symlink("link", "dir/file", simple_file_options)?;
+
+    let archive_path =
+        test_directory.write_archive("archive.zip", &zip_writer.finish()?.into_inner())?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    ensure_symlink_extracted(&test_directory)
+}
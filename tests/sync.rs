@@ -0,0 +1,66 @@
+//! Extracts archives with "--sync"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{TestDirectory, CONTENTS};
+use tar::{EntryType, Header};
+
+/// Files without read permission are synced too (which only matters when not running as root)
+#[test]
+fn tar_sync() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-sync")?;
+
+    let mut builder = tar::Builder::new(Vec::new());
+
+    common::append_tar_entry(
+        &mut builder,
+        b"dir/file",
+        EntryType::Regular,
+        None,
+        CONTENTS,
+    )?;
+
+    for (path, mode) in [("dir/unreadable", 0o000), ("dir/write-only", 0o200)] {
+        let mut header = Header::new_ustar();
+
+        header.set_path(path)?;
+        header.set_entry_type(EntryType::Regular);
+        header.set_mode(mode);
+        header.set_size(u64::try_from(CONTENTS.len())?);
+        header.set_cksum();
+
+        builder.append(&header, CONTENTS)?;
+    }
+
+    let archive_path = test_directory.write_archive("archive.tar", &builder.into_inner()?)?;
+
+    let output = test_directory.run_tarx(archive_path.as_path(), &["--sync"])?;
+
+    anyhow::ensure!(
+        String::from_utf8_lossy(&output.stderr).contains("Synced extracted entries to disk"),
+        "Sync was not reported"
+    );
+
+    common::ensure_extracted(test_directory.extracted("dir/file").as_path())?;
+
+    #[cfg(unix)]
+    {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        // The permissions are not changed by syncing
+        let modes = ["dir/unreadable", "dir/write-only"].map(|st| {
+            fs::symlink_metadata(test_directory.extracted(st))
+                .map(|me| me.permissions().mode() & 0o777)
+                .unwrap_or_default()
+        });
+
+        anyhow::ensure!(modes == [0o000, 0o200], "Modes are {modes:?}");
+    }
+
+    Ok(())
+}
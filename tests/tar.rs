@@ -0,0 +1,50 @@
+//! Extracts tar archives containing metadata entries
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{TestDirectory, CONTENTS};
+use tar::EntryType;
+
+/// `git archive` writes a PAX global header containing the commit ID, which is not an entry to be extracted
+#[test]
+fn tar_pax_global_header() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("tar-pax-global-header")?;
+
+    let mut builder = tar::Builder::new(Vec::new());
+
+    common::append_tar_entry(
+        &mut builder,
+        b"pax_global_header",
+        EntryType::XGlobalHeader,
+        None,
+        b"52 comment=e9d9103000000000000000000000000000000000\n",
+    )?;
+    common::append_tar_entry(&mut builder, b"repo/", EntryType::Directory, None, &[])?;
+    common::append_tar_entry(
+        &mut builder,
+        b"repo/file",
+        EntryType::Regular,
+        None,
+        CONTENTS,
+    )?;
+
+    let archive_path = test_directory.write_archive("archive.tar", &builder.into_inner()?)?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    common::ensure_extracted(test_directory.extracted("repo/file").as_path())?;
+    common::ensure_missing(test_directory.extracted("pax_global_header").as_path())?;
+
+    // The global header is not a second top-level entry
+    test_directory.run_tarx(archive_path.as_path(), &["--smart-unpack", "--merge"])?;
+
+    common::ensure_missing(test_directory.extracted("repo/repo").as_path())?;
+    common::ensure_extracted(test_directory.extracted("file").as_path())?;
+
+    Ok(())
+}
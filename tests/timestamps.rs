@@ -0,0 +1,94 @@
+//! Extracts zip and 7z entries, and checks their modification times
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{TestDirectory, CONTENTS};
+use sevenz_rust::{nt_time::FileTime, SevenZArchiveEntry, SevenZWriter};
+use std::{
+    fs,
+    io::Cursor,
+    time::{Duration, SystemTime},
+};
+
+#[test]
+fn seven_z_modification_time() -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new("seven-z-modification-time")?;
+
+    // 2024-07-01 12:00:00 UTC
+    let modification_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_719_835_200);
+
+    let mut seven_z_writer = SevenZWriter::new(Cursor::new(Vec::new()))?;
+
+    let mut seven_z_archive_entry = SevenZArchiveEntry::new();
+
+    "file".clone_into(&mut seven_z_archive_entry.name);
+    seven_z_archive_entry.has_last_modified_date = true;
+    seven_z_archive_entry.last_modified_date = FileTime::try_from(modification_time)?;
+
+    seven_z_writer.push_archive_entry(seven_z_archive_entry, Some(CONTENTS))?;
+
+    let archive_path =
+        test_directory.write_archive("archive.7z", &seven_z_writer.finish()?.into_inner())?;
+
+    test_directory.run_tarx(archive_path.as_path(), &[])?;
+
+    let modified = fs::metadata(test_directory.extracted("file"))?.modified()?;
+
+    anyhow::ensure!(
+        modified == modification_time,
+        "Modification time is {modified:?}"
+    );
+
+    Ok(())
+}
+
+/// The DOS time of zip entries is in local time, and is only used if no extra field stores the time
+#[cfg(unix)]
+#[test]
+fn zip_dos_time() -> anyhow::Result<()> {
+    use std::{io::Write, process::Command};
+    use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipWriter};
+
+    let test_directory = TestDirectory::new("zip-dos-time")?;
+
+    let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    zip_writer.start_file(
+        "file",
+        SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .last_modified_time(DateTime::from_date_and_time(2024, 7, 1, 12, 0, 0)?),
+    )?;
+    zip_writer.write_all(CONTENTS)?;
+
+    let archive_path =
+        test_directory.write_archive("archive.zip", &zip_writer.finish()?.into_inner())?;
+
+    // 5 hours behind UTC, without daylight saving time
+    let output = Command::new(env!("CARGO_BIN_EXE_tarx"))
+        .arg(archive_path.as_path())
+        .current_dir(test_directory.path.as_path())
+        .env("TZ", "TARX+5")
+        .output()?;
+
+    anyhow::ensure!(
+        output.status.success(),
+        "tarx failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let modified = fs::metadata(test_directory.extracted("file"))?.modified()?;
+
+    // 2024-07-01 17:00:00 UTC
+    anyhow::ensure!(
+        modified == SystemTime::UNIX_EPOCH + Duration::from_secs(1_719_853_200),
+        "Modification time is {modified:?}"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Extracts archives with "--transform"
+
+#![expect(
+    clippy::tests_outside_test_module,
+    reason = "Integration tests are not in a module"
+)]
+
+mod common;
+
+use common::{Format, TestDirectory};
+
+fn check_transform(format: Format) -> anyhow::Result<()> {
+    let test_directory = TestDirectory::new(format!("transform-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory
+        .write_format_archive(format, &["pkg-1.2/", "pkg-1.2/file", "pkg-1.2/src/file"])?;
+
+    test_directory.run_tarx(
+        archive_path.as_path(),
+        &["--transform", "s,^pkg-1.2/,pkg/,"],
+    )?;
+
+    common::ensure_extracted(test_directory.extracted("pkg/file").as_path())?;
+    common::ensure_extracted(test_directory.extracted("pkg/src/file").as_path())?;
+    common::ensure_missing(test_directory.extracted("pkg-1.2").as_path())?;
+
+    Ok(())
+}
+
+/// A transform cannot move an entry outside of the new directory
+fn check_transform_traversal(format: Format) -> anyhow::Result<()> {
+    let test_directory =
+        TestDirectory::new(format!("transform-traversal-{}", format.extension()).as_str())?;
+
+    let archive_path = test_directory.write_format_archive(format, &["file"])?;
+
+    test_directory.run_tarx(archive_path.as_path(), &["--transform", "s,^,../,"])?;
+
+    common::ensure_missing(test_directory.path.join("file").as_path())?;
+    common::ensure_missing(test_directory.extracted("file").as_path())?;
+
+    Ok(())
+}
+
+#[test]
+fn seven_z_transform() -> anyhow::Result<()> {
+    check_transform(Format::SevenZ)
+}
+
+#[test]
+fn seven_z_transform_traversal() -> anyhow::Result<()> {
+    check_transform_traversal(Format::SevenZ)
+}
+
+#[test]
+fn tar_transform() -> anyhow::Result<()> {
+    check_transform(Format::Tar)
+}
+
+#[test]
+fn tar_transform_traversal() -> anyhow::Result<()> {
+    check_transform_traversal(Format::Tar)
+}
+
+#[test]
+fn zip_transform() -> anyhow::Result<()> {
+    check_transform(Format::Zip)
+}
+
+#[test]
+fn zip_transform_traversal() -> anyhow::Result<()> {
+    check_transform_traversal(Format::Zip)
+}